@@ -30,7 +30,7 @@ fn spawn_static(b: &mut Bencher) {
     struct Bundle {
         pos: Position,
         vel: Velocity,
-    };
+    }
 
     let mut world = World::new();
     b.iter(|| {
@@ -46,7 +46,7 @@ fn spawn_batch(b: &mut Bencher) {
     struct Bundle {
         pos: Position,
         vel: Velocity,
-    };
+    }
 
     let mut world = World::new();
     b.iter(|| {
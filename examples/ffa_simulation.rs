@@ -43,7 +43,7 @@ struct KillCount {
 fn manhattan_dist(x0: i32, x1: i32, y0: i32, y1: i32) -> i32 {
     let dx = (x0 - x1).abs();
     let dy = (y0 - y1).abs();
-    return dx + dy;
+    dx + dy
 }
 
 // Spawns entities one by one. It's preferable to use spawn_batch instead, which has better performance for spawning multiple entities.
@@ -140,7 +140,7 @@ fn system_fire_at_closest(world: &mut World) {
             }
         }
 
-        if !closest_id.is_some() {
+        if closest_id.is_none() {
             println!("{:?} is the last survivor!", id0);
             return;
         }
@@ -158,7 +158,7 @@ fn system_fire_at_closest(world: &mut World) {
         // Is target unit still alive?
         if hp1.hp > 0 {
             // apply damage
-            hp1.hp = hp1.hp - dmg0.dmg;
+            hp1.hp -= dmg0.dmg;
             println!(
                 "Unit {:?} was damaged by {:?} for {:?} HP",
                 closest_id.unwrap(),
@@ -187,8 +187,8 @@ fn system_remove_dead(world: &mut World) {
         }
     }
 
-    for i in 0..to_remove.len() {
-        world.despawn(to_remove[i]).unwrap();
+    for entity in to_remove {
+        world.despawn(entity).unwrap();
     }
 }
 
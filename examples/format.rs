@@ -6,6 +6,7 @@ fn format_entity(entity: hecs::EntityRef<'_>) -> String {
         Some(entity.get::<T>()?.to_string())
     }
 
+    #[allow(clippy::type_complexity)]
     const FUNCTIONS: &[&dyn Fn(hecs::EntityRef<'_>) -> Option<String>] =
         &[&fmt::<i32>, &fmt::<bool>, &fmt::<f64>];
 
@@ -13,7 +14,7 @@ fn format_entity(entity: hecs::EntityRef<'_>) -> String {
     for f in FUNCTIONS {
         if let Some(x) = f(entity) {
             if out.is_empty() {
-                out.push_str("[");
+                out.push('[');
             } else {
                 out.push_str(", ");
             }
@@ -21,7 +22,7 @@ fn format_entity(entity: hecs::EntityRef<'_>) -> String {
         }
     }
     if out.is_empty() {
-        out.push_str(&"[]");
+        out.push_str("[]");
     } else {
         out.push(']');
     }
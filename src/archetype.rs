@@ -83,9 +83,7 @@ impl Archetype {
     pub(crate) fn get<T: Component>(&self) -> Option<NonNull<T>> {
         let state = self.state.get(&TypeId::of::<T>())?;
         Some(unsafe {
-            NonNull::new_unchecked(
-                (*self.data.get()).as_ptr().add(state.offset).cast::<T>() as *mut T
-            )
+            NonNull::new_unchecked((*self.data.get()).as_ptr().add(state.offset).cast::<T>())
         })
     }
 
@@ -93,7 +91,7 @@ impl Archetype {
         if self
             .state
             .get(&TypeId::of::<T>())
-            .map_or(false, |x| !x.borrow.borrow())
+            .is_some_and(|x| !x.borrow.borrow())
         {
             panic!("{} already borrowed uniquely", type_name::<T>());
         }
@@ -103,7 +101,7 @@ impl Archetype {
         if self
             .state
             .get(&TypeId::of::<T>())
-            .map_or(false, |x| !x.borrow.borrow_mut())
+            .is_some_and(|x| !x.borrow.borrow_mut())
         {
             panic!("{} already borrowed", type_name::<T>());
         }
@@ -121,10 +119,16 @@ impl Archetype {
         }
     }
 
-    pub(crate) fn len(&self) -> u32 {
+    /// Number of entities in this archetype
+    pub fn len(&self) -> u32 {
         self.len
     }
 
+    /// Whether this archetype contains no entities
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
     pub(crate) fn entities(&self) -> NonNull<u32> {
         unsafe { NonNull::new_unchecked(self.entities.as_ptr() as *mut _) }
     }
@@ -170,14 +174,27 @@ impl Archetype {
         }
     }
 
-    pub(crate) fn capacity(&self) -> u32 {
+    /// Number of entities that can be stored without reallocating
+    pub fn capacity(&self) -> u32 {
         self.entities.len() as u32
     }
 
+    /// Release any capacity beyond that needed for the current entities
+    pub(crate) fn shrink_to_fit(&mut self) {
+        if self.capacity() != self.len {
+            self.realloc(self.len as usize);
+        }
+    }
+
     fn grow(&mut self, increment: u32) {
+        self.realloc(self.len as usize + increment as usize);
+    }
+
+    /// Move storage into a new allocation with room for exactly `count` entities
+    fn realloc(&mut self, count: usize) {
+        debug_assert!(count >= self.len as usize);
         unsafe {
             let old_count = self.len as usize;
-            let count = old_count + increment as usize;
             let mut new_entities = vec![!0; count].into_boxed_slice();
             new_entities[0..old_count].copy_from_slice(&self.entities[0..old_count]);
             self.entities = new_entities;
@@ -190,7 +207,9 @@ impl Archetype {
                 self.data_size += ty.layout.size() * count;
             }
             let new_data = if self.data_size == 0 {
-                NonNull::dangling()
+                // Only zero-sized components, which still require well-aligned pointers
+                let align = self.types.first().map_or(1, |x| x.layout.align());
+                NonNull::new(align as *mut u8).unwrap()
             } else {
                 NonNull::new(alloc(
                     Layout::from_size_align(
@@ -211,6 +230,13 @@ impl Archetype {
                         ty.layout.size() * old_count,
                     );
                 }
+                dealloc(
+                    (*self.data.get()).as_ptr().cast(),
+                    Layout::from_size_align_unchecked(
+                        old_data_size,
+                        self.types.first().map_or(1, |x| x.layout.align()),
+                    ),
+                );
             }
 
             self.data = UnsafeCell::new(new_data);
@@ -278,6 +304,24 @@ impl Archetype {
         }
     }
 
+    /// Exchange the rows at `a` and `b`
+    ///
+    /// Both indices must be in-bounds
+    pub(crate) unsafe fn swap(&mut self, a: u32, b: u32) {
+        if a == b {
+            return;
+        }
+        for ty in &self.types {
+            let size = ty.layout.size();
+            ptr::swap_nonoverlapping(
+                self.get_dynamic(ty.id, size, a).unwrap().as_ptr(),
+                self.get_dynamic(ty.id, size, b).unwrap().as_ptr(),
+                size,
+            );
+        }
+        self.entities.swap(a as usize, b as usize);
+    }
+
     pub(crate) unsafe fn put_dynamic(
         &mut self,
         component: *mut u8,
@@ -293,6 +337,15 @@ impl Archetype {
         ptr::copy_nonoverlapping(component, ptr, size);
     }
 
+    /// The types of the components stored in this archetype
+    ///
+    /// Columns are always kept in one canonical order, by descending alignment and then by
+    /// `TypeId`. The order is the same no matter which path created the archetype. It is not
+    /// stable between builds, since `TypeId`s are not.
+    pub fn component_types(&self) -> impl ExactSizeIterator<Item = TypeId> + '_ {
+        self.types.iter().map(|x| x.id)
+    }
+
     /// How, if at all, `Q` will access entities in this archetype
     pub fn access<Q: Query>(&self) -> Option<Access> {
         Q::Fetch::access(self)
@@ -390,7 +443,7 @@ impl PartialEq for TypeInfo {
 
 impl Eq for TypeInfo {}
 
-fn align(x: usize, alignment: usize) -> usize {
+pub(crate) fn align(x: usize, alignment: usize) -> usize {
     debug_assert!(alignment.is_power_of_two());
     (x + alignment - 1) & (!alignment + 1)
 }
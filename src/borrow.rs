@@ -12,6 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use core::any::TypeId;
+use core::marker::PhantomData;
 use core::ops::{Deref, DerefMut};
 use core::ptr::NonNull;
 use core::sync::atomic::{AtomicUsize, Ordering};
@@ -58,7 +60,7 @@ impl AtomicBorrow {
     }
 }
 
-const UNIQUE_BIT: usize = !(usize::max_value() >> 1);
+const UNIQUE_BIT: usize = !(usize::MAX >> 1);
 
 /// Shared borrow of an entity's component
 #[derive(Clone)]
@@ -72,6 +74,7 @@ impl<'a, T: Component> Ref<'a, T> {
         archetype: &'a Archetype,
         index: u32,
     ) -> Result<Self, MissingComponent> {
+        debug_assert!(index < archetype.len(), "index out of bounds");
         let target = NonNull::new_unchecked(
             archetype
                 .get::<T>()
@@ -111,6 +114,7 @@ impl<'a, T: Component> RefMut<'a, T> {
         archetype: &'a Archetype,
         index: u32,
     ) -> Result<Self, MissingComponent> {
+        debug_assert!(index < archetype.len(), "index out of bounds");
         let target = NonNull::new_unchecked(
             archetype
                 .get::<T>()
@@ -182,7 +186,91 @@ impl<'a> EntityRef<'a> {
     pub fn get_mut<T: Component>(&self) -> Option<RefMut<'a, T>> {
         Some(unsafe { RefMut::new(self.archetype?, self.index).ok()? })
     }
+
+    /// The types of the entity's components, in arbitrary order
+    ///
+    /// Combined with iterating over the `World`, this visits every entity along with its full set
+    /// of components without borrowing any of them, e.g. for generic serialization.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// # use std::any::TypeId;
+    /// let mut world = World::new();
+    /// let e = world.spawn((123, true));
+    /// let mut types = world.entity(e).unwrap().component_types().collect::<Vec<_>>();
+    /// types.sort();
+    /// let mut expected = vec![TypeId::of::<i32>(), TypeId::of::<bool>()];
+    /// expected.sort();
+    /// assert_eq!(types, expected);
+    /// ```
+    pub fn component_types(&self) -> impl Iterator<Item = TypeId> + 'a {
+        self.archetype
+            .map(|x| x.types())
+            .unwrap_or(&[])
+            .iter()
+            .map(|x| x.id())
+    }
 }
 
 unsafe impl<'a> Send for EntityRef<'a> {}
 unsafe impl<'a> Sync for EntityRef<'a> {}
+
+/// Exclusive, type-erased access to all of an entity's components
+///
+/// Obtained from `World::components_mut` or `World::query_components_mut`. Useful for tooling that must modify components whose
+/// types aren't statically known.
+pub struct ComponentsMut<'a> {
+    archetype: Option<&'a Archetype>,
+    index: u32,
+    _marker: PhantomData<&'a mut ()>,
+}
+
+impl<'a> ComponentsMut<'a> {
+    /// Construct access to an entity with no components
+    pub(crate) fn empty() -> Self {
+        Self {
+            archetype: None,
+            index: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `index` must be in-bounds for `archetype`, and no other access to that entity's components
+    /// may occur while the result is live
+    pub(crate) unsafe fn new(archetype: &'a Archetype, index: u32) -> Self {
+        Self {
+            archetype: Some(archetype),
+            index,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The types of the entity's components, in arbitrary order
+    pub fn component_types(&self) -> impl Iterator<Item = TypeId> + 'a {
+        self.archetype
+            .map(|x| x.types())
+            .unwrap_or(&[])
+            .iter()
+            .map(|x| x.id())
+    }
+
+    /// Get a pointer to the component with type `id`, if it exists
+    ///
+    /// The pointer is valid for reads and writes of the component's type for as long as `self` is
+    /// live. Overwriting the component without first dropping it leaks the old value.
+    pub fn get_mut_by_id(&mut self, id: TypeId) -> Option<*mut u8> {
+        let archetype = self.archetype?;
+        let ty = archetype.types().iter().find(|x| x.id() == id)?;
+        unsafe {
+            archetype
+                .get_dynamic(id, ty.layout().size(), self.index)
+                .map(|x| x.as_ptr())
+        }
+    }
+}
+
+unsafe impl<'a> Send for ComponentsMut<'a> {}
+unsafe impl<'a> Sync for ComponentsMut<'a> {}
@@ -59,6 +59,18 @@ pub trait Bundle: DynamicBundle {
         Self: Sized;
 }
 
+/// A collection of borrowed components that can be cloned into a new bundle
+///
+/// Implemented for tuples of references, allowing entities to be spawned from borrowed templates
+/// with `World::spawn_cloned`.
+pub trait CloneBundle {
+    /// The bundle produced by cloning each referenced component
+    type Bundle: Bundle;
+
+    /// Clone every referenced component into an owned bundle
+    fn clone_bundle(&self) -> Self::Bundle;
+}
+
 /// Error indicating that an entity did not have a required component
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct MissingComponent(&'static str);
@@ -109,16 +121,24 @@ macro_rules! tuple_impl {
         impl<$($name: Component),*> Bundle for ($($name,)*) {
             fn with_static_ids<T>(f: impl FnOnce(&[TypeId]) -> T) -> T {
                 const N: usize = count!($($name),*);
-                let mut xs: [(usize, TypeId); N] = [$((mem::align_of::<$name>(), TypeId::of::<$name>())),*];
+                let mut xs: [(usize, TypeId, &str); N] = [$((mem::align_of::<$name>(), TypeId::of::<$name>(), type_name::<$name>())),*];
                 xs.sort_unstable_by(|x, y| x.0.cmp(&y.0).reverse().then(x.1.cmp(&y.1)));
+                // Equal types have equal alignment, so any duplicates are now adjacent
+                for pair in xs.windows(2) {
+                    if pair[0].1 == pair[1].1 {
+                        panic!("{} has multiple {} fields; each type must occur at most once!", type_name::<Self>(), pair[0].2);
+                    }
+                }
                 let mut ids = [TypeId::of::<()>(); N];
-                for (slot, &(_, id)) in ids.iter_mut().zip(xs.iter()) {
+                for (slot, &(_, id, _)) in ids.iter_mut().zip(xs.iter()) {
                     *slot = id;
                 }
                 f(&ids)
             }
 
             fn static_type_info() -> Vec<TypeInfo> {
+                // Reject duplicate types
+                Self::with_static_ids(|_| ());
                 let mut xs = vec![$(TypeInfo::of::<$name>()),*];
                 xs.sort_unstable();
                 xs
@@ -135,6 +155,17 @@ macro_rules! tuple_impl {
                 Ok(($($name.read(),)*))
             }
         }
+
+        impl<'a, $($name: Component + Clone),*> CloneBundle for ($(&'a $name,)*) {
+            type Bundle = ($($name,)*);
+
+            #[allow(clippy::unused_unit)]
+            fn clone_bundle(&self) -> Self::Bundle {
+                #[allow(non_snake_case)]
+                let ($($name,)*) = *self;
+                ($($name.clone(),)*)
+            }
+        }
     }
 }
 
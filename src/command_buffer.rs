@@ -0,0 +1,315 @@
+// Copyright 2019 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::alloc::collections::VecDeque;
+use crate::alloc::vec::Vec;
+use core::any::TypeId;
+use core::ops::Range;
+
+use crate::archetype::TypeInfo;
+use crate::entity_builder::ComponentBuffer;
+use crate::{Bundle, DynamicBundle, Entity, Query, QueryBorrow, World};
+
+/// Records operations for future application to a `World`
+///
+/// Useful when operations cannot be applied directly due to ordering concerns or borrow checking,
+/// e.g. when spawning or despawning entities while iterating a query. Commands are applied by
+/// `World::apply` in the order they were recorded.
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// let mut world = World::new();
+/// let a = world.spawn((123,));
+/// let mut cmd = CommandBuffer::new();
+/// for (id, &x) in world.query::<&i32>().iter() {
+///     let child = cmd.spawn(&world, (x + 1,));
+///     cmd.insert(id, (child,));
+/// }
+/// world.apply(cmd);
+/// let child = *world.get::<Entity>(a).unwrap();
+/// assert_eq!(*world.get::<i32>(child).unwrap(), 124);
+/// ```
+pub struct CommandBuffer {
+    storage: ComponentBuffer,
+    /// Type and offset in `storage` of each recorded component
+    components: Vec<(TypeInfo, usize)>,
+    /// Scratch space for the IDs of a single entity's components
+    ids: Vec<TypeId>,
+    commands: VecDeque<Command>,
+}
+
+impl CommandBuffer {
+    /// Create an empty command buffer
+    pub fn new() -> Self {
+        Self {
+            storage: ComponentBuffer::new(),
+            components: Vec::new(),
+            ids: Vec::new(),
+            commands: VecDeque::new(),
+        }
+    }
+
+    /// Reserve an entity in `world` and record the addition of `components` to it
+    ///
+    /// The returned handle is usable immediately, e.g. to be stored in other components, though
+    /// the entity has no components until the buffer is applied. If the buffer is dropped without
+    /// being applied, the entity remains with no components.
+    pub fn spawn(&mut self, world: &World, components: impl DynamicBundle) -> Entity {
+        let entity = world.reserve_entity();
+        self.insert(entity, components);
+        entity
+    }
+
+    /// Record the addition of `components` to `entity`, as if by `World::insert`
+    pub fn insert(&mut self, entity: Entity, components: impl DynamicBundle) {
+        let start = self.components.len();
+        let info = components.type_info();
+        unsafe {
+            components.put(|ptr, id, _| {
+                let ty = *info.iter().find(|x| x.id() == id).unwrap();
+                let offset = self.storage.add_dynamic(ty, ptr);
+                self.components.push((ty, offset));
+                true
+            });
+        }
+        self.components[start..].sort_unstable_by_key(|x| x.0);
+        self.commands.push_back(Command::Insert {
+            entity,
+            components: start..self.components.len(),
+        });
+    }
+
+    /// Record the despawning of `entity`, as if by `World::despawn`
+    pub fn despawn(&mut self, entity: Entity) {
+        self.commands.push_back(Command::Despawn(entity));
+    }
+
+    /// Record the removal of components `T` from `entity`, as if by `World::remove`
+    ///
+    /// The removed components are dropped.
+    pub fn remove<T: Bundle>(&mut self, entity: Entity) {
+        self.commands.push_back(Command::Remove {
+            entity,
+            remove: |world, entity| {
+                let _ = world.remove::<T>(entity);
+            },
+        });
+    }
+
+    /// Apply all recorded commands to `world`, in order
+    ///
+    /// Each command is applied exactly as the corresponding `World` method would be, except that
+    /// failures are ignored: by the time a buffer is applied, earlier commands or other code may
+    /// legitimately have despawned the target entity, or removed the components to be removed.
+    /// Specifically, an `insert` into an entity that no longer exists drops the recorded
+    /// components, a `despawn` of an entity that no longer exists does nothing, and a `remove`
+    /// from an entity that no longer exists or lacks any of the components does nothing.
+    ///
+    /// If applying a command panics, e.g. because a component's `Drop` impl does, the commands
+    /// not yet applied remain recorded, so their components are dropped along with the buffer.
+    pub(crate) fn run_on(&mut self, world: &mut World) {
+        while let Some(command) = self.commands.pop_front() {
+            match command {
+                Command::Insert { entity, components } => {
+                    let components = &self.components[components];
+                    self.ids.clear();
+                    self.ids.extend(components.iter().map(|x| x.0.id()));
+                    let _ = world.insert(
+                        entity,
+                        RecordedEntity {
+                            storage: &self.storage,
+                            components,
+                            ids: &self.ids,
+                            consumed: false,
+                        },
+                    );
+                }
+                Command::Despawn(entity) => {
+                    let _ = world.despawn(entity);
+                }
+                Command::Remove { entity, remove } => remove(world, entity),
+            }
+        }
+        self.components.clear();
+        self.storage.clear();
+    }
+}
+
+unsafe impl Send for CommandBuffer {}
+unsafe impl Sync for CommandBuffer {}
+
+impl Drop for CommandBuffer {
+    fn drop(&mut self) {
+        // Drop components recorded by commands that were never applied
+        for command in &self.commands {
+            if let Command::Insert { components, .. } = command {
+                for &(ty, offset) in &self.components[components.clone()] {
+                    unsafe {
+                        ty.drop(self.storage.get(offset));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Default for CommandBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Shared access to a `World` together with a `CommandBuffer` for deferred changes to it
+///
+/// A convenient signature for systems: they can read and query the world, reserve entities, and
+/// record structural changes, which are all applied together once the system is done.
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// fn split(ctx: &mut SystemContext<'_>) {
+///     for (id, &size) in ctx.query::<&u32>().iter() {
+///         if size > 1 {
+///             ctx.spawn((size / 2,));
+///             ctx.insert(id, (size - size / 2,));
+///         }
+///     }
+/// }
+///
+/// let mut world = World::new();
+/// world.spawn((5u32,));
+/// let mut ctx = SystemContext::new(&world);
+/// split(&mut ctx);
+/// let cmd = ctx.into_commands();
+/// world.apply(cmd);
+/// assert_eq!(world.query::<&u32>().iter().map(|(_, &x)| x).sum::<u32>(), 5);
+/// ```
+pub struct SystemContext<'w> {
+    world: &'w World,
+    commands: CommandBuffer,
+}
+
+impl<'w> SystemContext<'w> {
+    /// Create a context for reading `world` and recording changes to it
+    pub fn new(world: &'w World) -> Self {
+        Self {
+            world,
+            commands: CommandBuffer::new(),
+        }
+    }
+
+    /// The world being read
+    pub fn world(&self) -> &'w World {
+        self.world
+    }
+
+    /// Query the world, as if by `World::query`
+    ///
+    /// The result borrows only the world, so commands can be recorded while iterating it.
+    pub fn query<Q: Query>(&self) -> QueryBorrow<'w, Q> {
+        self.world.query()
+    }
+
+    /// Reserve an entity, as if by `World::reserve_entity`
+    pub fn reserve_entity(&self) -> Entity {
+        self.world.reserve_entity()
+    }
+
+    /// Reserve an entity and record the addition of `components` to it
+    ///
+    /// See `CommandBuffer::spawn`.
+    pub fn spawn(&mut self, components: impl DynamicBundle) -> Entity {
+        self.commands.spawn(self.world, components)
+    }
+
+    /// Record the addition of `components` to `entity`
+    pub fn insert(&mut self, entity: Entity, components: impl DynamicBundle) {
+        self.commands.insert(entity, components);
+    }
+
+    /// Record the despawning of `entity`
+    pub fn despawn(&mut self, entity: Entity) {
+        self.commands.despawn(entity);
+    }
+
+    /// Record the removal of components `T` from `entity`
+    pub fn remove<T: Bundle>(&mut self, entity: Entity) {
+        self.commands.remove::<T>(entity);
+    }
+
+    /// Access the underlying command buffer
+    pub fn commands(&mut self) -> &mut CommandBuffer {
+        &mut self.commands
+    }
+
+    /// Release the world, yielding the recorded commands for `World::apply`
+    pub fn into_commands(self) -> CommandBuffer {
+        self.commands
+    }
+}
+
+enum Command {
+    /// Add the components in the given range of `CommandBuffer::components`
+    Insert {
+        entity: Entity,
+        components: Range<usize>,
+    },
+    Despawn(Entity),
+    Remove {
+        entity: Entity,
+        remove: fn(&mut World, Entity),
+    },
+}
+
+/// The components of a single `Command::Insert`, in `TypeInfo` order
+struct RecordedEntity<'a> {
+    storage: &'a ComponentBuffer,
+    components: &'a [(TypeInfo, usize)],
+    ids: &'a [TypeId],
+    consumed: bool,
+}
+
+impl DynamicBundle for RecordedEntity<'_> {
+    fn with_ids<T>(&self, f: impl FnOnce(&[TypeId]) -> T) -> T {
+        f(self.ids)
+    }
+
+    fn type_info(&self) -> Vec<TypeInfo> {
+        self.components.iter().map(|x| x.0).collect()
+    }
+
+    unsafe fn put(mut self, mut f: impl FnMut(*mut u8, TypeId, usize) -> bool) {
+        self.consumed = true;
+        for &(ty, offset) in self.components {
+            let ptr = self.storage.get(offset);
+            if !f(ptr, ty.id(), ty.layout().size()) {
+                ty.drop(ptr);
+            }
+        }
+    }
+}
+
+impl Drop for RecordedEntity<'_> {
+    fn drop(&mut self) {
+        // Ensures components aren't leaked if the target entity no longer exists
+        if !self.consumed {
+            for &(ty, offset) in self.components {
+                unsafe {
+                    ty.drop(self.storage.get(offset));
+                }
+            }
+        }
+    }
+}
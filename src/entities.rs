@@ -1,6 +1,7 @@
 use alloc::boxed::Box;
 use alloc::vec::Vec;
 use core::convert::TryFrom;
+use core::num::NonZeroU32;
 use core::sync::atomic::{AtomicU32, Ordering};
 use core::{fmt, mem};
 #[cfg(feature = "std")]
@@ -9,31 +10,43 @@ use std::error::Error;
 /// Lightweight unique ID of an entity
 ///
 /// Obtained from `World::spawn`. Can be stored to refer to an entity in the future.
+///
+/// Generations start at 1, so `Option<Entity>` is no larger than `Entity`.
 #[derive(Clone, Copy, Hash, Eq, Ord, PartialEq, PartialOrd)]
 pub struct Entity {
-    pub(crate) generation: u32,
+    pub(crate) generation: NonZeroU32,
     pub(crate) id: u32,
 }
 
 impl Entity {
     /// Convert to a form convenient for passing outside of rust
     ///
-    /// Only useful for identifying entities within the same instance of an application. Do not use
-    /// for serialization between runs.
+    /// The layout is stable: the generation occupies the high 32 bits and the ID the low 32 bits,
+    /// so clients in other languages may construct or decompose these values directly. A handle
+    /// identifies an entity only within the `World` that produced it.
     ///
-    /// No particular structure is guaranteed for the returned bits.
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let e = world.spawn(());
+    /// let bits = e.to_bits();
+    /// assert_eq!(bits as u32, e.id());
+    /// assert_eq!(Entity::from_bits(bits), Some(e));
+    /// ```
     pub fn to_bits(self) -> u64 {
-        u64::from(self.generation) << 32 | u64::from(self.id)
+        u64::from(self.generation.get()) << 32 | u64::from(self.id)
     }
 
     /// Reconstruct an `Entity` previously destructured with `to_bits`
     ///
-    /// Only useful when applied to results from `to_bits` in the same instance of an application.
-    pub fn from_bits(bits: u64) -> Self {
-        Self {
-            generation: (bits >> 32) as u32,
+    /// See `to_bits` for the bit layout. Returns `None` if the generation is zero, which no entity
+    /// ever has.
+    pub fn from_bits(bits: u64) -> Option<Self> {
+        Some(Self {
+            generation: NonZeroU32::new((bits >> 32) as u32)?,
             id: bits as u32,
-        }
+        })
     }
 
     /// Extract a transiently unique identifier
@@ -55,7 +68,7 @@ impl fmt::Debug for Entity {
 #[derive(Default)]
 pub(crate) struct Entities {
     pub meta: Box<[EntityMeta]>,
-    // Reserved entities outside the range of `meta`, having implicit generation 0, archetype 0, and
+    // Reserved entities outside the range of `meta`, having implicit generation 1, archetype 0, and
     // undefined index. Calling `flush` converts these to real entities, which can have a fully
     // defined location.
     pending: AtomicU32,
@@ -83,7 +96,7 @@ impl Entities {
                 None => {
                     let n = self.pending.fetch_add(1, Ordering::Relaxed);
                     return Entity {
-                        generation: 0,
+                        generation: EntityMeta::EMPTY.generation,
                         id: u32::try_from(self.meta.len())
                             .ok()
                             .and_then(|x| x.checked_add(n))
@@ -146,6 +159,40 @@ impl Entities {
         }
     }
 
+    /// Allocate a specific entity ID, growing storage as needed
+    ///
+    /// Returns the location of the live entity previously using `entity.id`, if any, which is
+    /// thereby implicitly freed. Location should be written immediately.
+    ///
+    /// Panics if `entity.id` is `u32::MAX`, since the number of IDs must fit in a `u32`.
+    pub fn alloc_at(&mut self, entity: Entity) -> Option<Location> {
+        debug_assert_eq!(
+            self.pending.load(Ordering::Relaxed),
+            0,
+            "allocator must be flushed before potentially growing"
+        );
+        assert!(entity.id != u32::MAX, "entity ID {} is reserved", u32::MAX);
+        let needed = entity.id as usize + 1;
+        if needed > self.meta.len() {
+            // Can't truncate, as `needed` is at most `u32::MAX`
+            self.grow((needed - self.meta.len()) as u32);
+        }
+        // Not racey due to &mut self
+        let free_cursor = self.free_cursor.load(Ordering::Relaxed);
+        let free = &mut self.free[..free_cursor as usize];
+        let previous = match free.iter().position(|&x| x == entity.id) {
+            Some(i) => {
+                // Swap-remove from the free list
+                free.swap(i, free_cursor as usize - 1);
+                self.free_cursor.store(free_cursor - 1, Ordering::Relaxed);
+                None
+            }
+            None => Some(self.meta[entity.id as usize].location),
+        };
+        self.meta[entity.id as usize].generation = entity.generation;
+        previous
+    }
+
     /// Destroy an entity, allowing it to be reused
     ///
     /// Must not be called on reserved entities prior to `flush`.
@@ -154,19 +201,19 @@ impl Entities {
         if meta.generation != entity.generation {
             return Err(NoSuchEntity);
         }
-        meta.generation += 1;
+        meta.generation = next_generation(meta.generation);
         let loc = mem::replace(
             &mut meta.location,
             Location {
                 archetype: 0,
                 // Guard against bugs in reservation handling
-                index: u32::max_value(),
+                index: u32::MAX,
             },
         );
         let index = self.free_cursor.fetch_add(1, Ordering::Relaxed); // Not racey due to &mut self
         self.free[index as usize] = entity.id;
         debug_assert!(
-            loc.index != u32::max_value(),
+            loc.index != u32::MAX,
             "free called on reserved entity without flush"
         );
         Ok(loc)
@@ -185,6 +232,12 @@ impl Entities {
         }
     }
 
+    /// Number of live entities, including those reserved but not yet flushed
+    pub fn len(&self) -> u32 {
+        self.meta.len() as u32 - self.free_cursor.load(Ordering::Relaxed)
+            + self.pending.load(Ordering::Relaxed)
+    }
+
     pub fn contains(&self, entity: Entity) -> bool {
         if entity.id >= self.meta.len() as u32 {
             return true;
@@ -196,8 +249,10 @@ impl Entities {
         // Not racey due to &mut self
         self.free_cursor
             .store(self.meta.len() as u32, Ordering::Relaxed);
+        // Reverse order, as in `grow`, so that low IDs are reused first
+        let len = self.meta.len() as u32;
         for (i, x) in self.free.iter_mut().enumerate() {
-            *x = i as u32;
+            *x = len - 1 - i as u32;
         }
         self.pending.store(0, Ordering::Relaxed);
         self.reserved_cursor.store(0, Ordering::Relaxed);
@@ -220,7 +275,7 @@ impl Entities {
         if self.meta.len() <= entity.id as usize {
             return Ok(Location {
                 archetype: 0,
-                index: u32::max_value(),
+                index: u32::MAX,
             });
         }
         let meta = &self.meta[entity.id as usize];
@@ -230,7 +285,7 @@ impl Entities {
         if meta.location.archetype == 0 {
             return Ok(Location {
                 archetype: 0,
-                index: u32::max_value(),
+                index: u32::MAX,
             });
         }
         Ok(meta.location)
@@ -273,16 +328,7 @@ impl Entities {
             .max(1024);
         let mut new_meta = Vec::with_capacity(new_len);
         new_meta.extend_from_slice(&self.meta);
-        new_meta.resize(
-            new_len,
-            EntityMeta {
-                generation: 0,
-                location: Location {
-                    archetype: 0,
-                    index: u32::max_value(), // dummy value, to be filled in
-                },
-            },
-        );
+        new_meta.resize(new_len, EntityMeta::EMPTY);
 
         let free_cursor = self.free_cursor.load(Ordering::Relaxed); // Not racey due to &mut self
         let mut new_free = Vec::with_capacity(new_len);
@@ -311,10 +357,28 @@ impl Entities {
 
 #[derive(Copy, Clone)]
 pub(crate) struct EntityMeta {
-    pub generation: u32,
+    pub generation: NonZeroU32,
     pub location: Location,
 }
 
+impl EntityMeta {
+    const EMPTY: EntityMeta = EntityMeta {
+        generation: match NonZeroU32::new(1) {
+            Some(x) => x,
+            None => unreachable!(),
+        },
+        location: Location {
+            archetype: 0,
+            index: u32::MAX, // dummy value, to be filled in
+        },
+    };
+}
+
+/// The generation following `generation`, wrapping around to 1
+pub(crate) fn next_generation(generation: NonZeroU32) -> NonZeroU32 {
+    NonZeroU32::new(generation.get().wrapping_add(1)).unwrap_or(EntityMeta::EMPTY.generation)
+}
+
 #[derive(Copy, Clone)]
 pub(crate) struct Location {
     pub archetype: u32,
@@ -341,9 +405,15 @@ mod tests {
     #[test]
     fn entity_bits_roundtrip() {
         let e = Entity {
-            generation: 0xDEADBEEF,
+            generation: NonZeroU32::new(0xDEADBEEF).unwrap(),
             id: 0xBAADF00D,
         };
-        assert_eq!(Entity::from_bits(e.to_bits()), e);
+        assert_eq!(Entity::from_bits(e.to_bits()), Some(e));
+    }
+
+    #[test]
+    fn generation_wraps_to_one() {
+        let last = NonZeroU32::new(u32::MAX).unwrap();
+        assert_eq!(next_generation(last).get(), 1);
     }
 }
@@ -13,15 +13,14 @@
 // limitations under the License.
 
 use crate::alloc::alloc::{alloc, dealloc, Layout};
-use crate::alloc::boxed::Box;
-use crate::alloc::{vec, vec::Vec};
-use core::any::TypeId;
-use core::mem::{self, MaybeUninit};
-use core::ptr;
+use crate::alloc::vec::Vec;
+use core::any::{type_name, TypeId};
+use core::mem;
+use core::ptr::{self, NonNull};
 
-use hashbrown::HashSet;
+use hashbrown::HashMap;
 
-use crate::archetype::TypeInfo;
+use crate::archetype::{align, TypeInfo};
 use crate::{Component, DynamicBundle};
 
 /// Helper for incrementally constructing a bundle of components with dynamic component types
@@ -38,53 +37,98 @@ use crate::{Component, DynamicBundle};
 /// assert_eq!(*world.get::<&str>(e).unwrap(), "abc");
 /// ```
 pub struct EntityBuilder {
-    storage: Box<[MaybeUninit<u8>]>,
-    cursor: usize,
+    storage: ComponentBuffer,
     info: Vec<(TypeInfo, usize)>,
     ids: Vec<TypeId>,
-    id_set: HashSet<TypeId>,
+    /// Index in `info` of each added type
+    indices: HashMap<TypeId, usize>,
 }
 
 impl EntityBuilder {
     /// Create a builder representing an entity with no components
     pub fn new() -> Self {
         Self {
-            cursor: 0,
-            storage: Box::new([]),
+            storage: ComponentBuffer::new(),
             info: Vec::new(),
             ids: Vec::new(),
-            id_set: HashSet::new(),
+            indices: HashMap::default(),
         }
     }
 
     /// Add `component` to the entity
+    ///
+    /// Panics if a `T` has already been added, since an entity can hold at most one component of
+    /// each type.
     pub fn add<T: Component>(&mut self, component: T) -> &mut Self {
-        if !self.id_set.insert(TypeId::of::<T>()) {
-            return self;
+        if self.indices.contains_key(&TypeId::of::<T>()) {
+            panic!(
+                "EntityBuilder already has a {} component; each type must occur at most once!",
+                type_name::<T>()
+            );
         }
-        let end = self.cursor + mem::size_of::<T>();
-        if end > self.storage.len() {
-            self.grow(end);
-        }
-        if mem::size_of::<T>() != 0 {
-            unsafe {
-                self.storage
-                    .as_mut_ptr()
-                    .add(self.cursor)
-                    .cast::<T>()
-                    .write_unaligned(component);
-            }
+        let mut component = mem::ManuallyDrop::new(component);
+        unsafe {
+            self.add_dynamic(TypeInfo::of::<T>(), (&mut *component as *mut T).cast());
         }
-        self.info.push((TypeInfo::of::<T>(), self.cursor));
-        self.cursor += mem::size_of::<T>();
         self
     }
 
-    fn grow(&mut self, min_size: usize) {
-        let new_len = min_size.next_power_of_two().max(64);
-        let mut new_storage = vec![MaybeUninit::uninit(); new_len].into_boxed_slice();
-        new_storage[..self.cursor].copy_from_slice(&self.storage[..self.cursor]);
-        self.storage = new_storage;
+    /// Move a component of type `ty` out of `component` into the builder
+    ///
+    /// # Safety
+    ///
+    /// `component` must point to a valid value of type `ty`, which must not already be present.
+    /// Ownership of the value passes to the builder.
+    unsafe fn add_dynamic(&mut self, ty: TypeInfo, component: *mut u8) {
+        let offset = self.storage.add_dynamic(ty, component);
+        self.indices.insert(ty.id(), self.info.len());
+        self.info.push((ty, offset));
+    }
+
+    /// Whether a `T` has been added
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut builder = EntityBuilder::new();
+    /// builder.add(123);
+    /// assert!(builder.has::<i32>());
+    /// assert!(!builder.has::<bool>());
+    /// ```
+    pub fn has<T: Component>(&self) -> bool {
+        self.indices.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Borrow the previously added `T`, if any
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut builder = EntityBuilder::new();
+    /// builder.add(123);
+    /// assert_eq!(builder.get::<i32>(), Some(&123));
+    /// assert_eq!(builder.get::<bool>(), None);
+    /// ```
+    pub fn get<T: Component>(&self) -> Option<&T> {
+        let index = *self.indices.get(&TypeId::of::<T>())?;
+        let offset = self.info[index].1;
+        unsafe { Some(&*self.storage.get(offset).cast::<T>()) }
+    }
+
+    /// Uniquely borrow the previously added `T`, if any
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut builder = EntityBuilder::new();
+    /// builder.add(123);
+    /// *builder.get_mut::<i32>().unwrap() += 1;
+    /// assert_eq!(builder.get::<i32>(), Some(&124));
+    /// ```
+    pub fn get_mut<T: Component>(&mut self) -> Option<&mut T> {
+        let index = *self.indices.get(&TypeId::of::<T>())?;
+        let offset = self.info[index].1;
+        unsafe { Some(&mut *self.storage.get(offset).cast::<T>()) }
     }
 
     /// Construct a `Bundle` suitable for spawning
@@ -100,41 +144,13 @@ impl EntityBuilder {
     /// be called.
     pub fn clear(&mut self) {
         self.ids.clear();
-        self.id_set.clear();
-        self.cursor = 0;
-        let max_size = self
-            .info
-            .iter()
-            .map(|x| x.0.layout().size())
-            .max()
-            .unwrap_or(0);
-        let max_align = self
-            .info
-            .iter()
-            .map(|x| x.0.layout().align())
-            .max()
-            .unwrap_or(0);
-        unsafe {
-            // Suitably aligned storage for drop
-            let tmp = if max_size > 0 {
-                alloc(Layout::from_size_align(max_size, max_align).unwrap()).cast()
-            } else {
-                max_align as *mut _
-            };
-            for (ty, offset) in self.info.drain(..) {
-                ptr::copy_nonoverlapping(
-                    self.storage[offset..offset + ty.layout().size()]
-                        .as_ptr()
-                        .cast(),
-                    tmp,
-                    ty.layout().size(),
-                );
-                ty.drop(tmp);
-            }
-            if max_size > 0 {
-                dealloc(tmp, Layout::from_size_align(max_size, max_align).unwrap())
+        self.indices.clear();
+        for (ty, offset) in self.info.drain(..) {
+            unsafe {
+                ty.drop(self.storage.get(offset));
             }
         }
+        self.storage.clear();
     }
 }
 
@@ -171,7 +187,7 @@ impl DynamicBundle for BuiltEntity<'_> {
 
     unsafe fn put(self, mut f: impl FnMut(*mut u8, TypeId, usize) -> bool) {
         for (ty, offset) in self.builder.info.drain(..) {
-            let ptr = self.builder.storage.as_mut_ptr().add(offset).cast();
+            let ptr = self.builder.storage.get(offset);
             if !f(ptr, ty.id(), ty.layout().size()) {
                 ty.drop(ptr);
             }
@@ -186,3 +202,139 @@ impl Drop for BuiltEntity<'_> {
         self.builder.clear();
     }
 }
+
+/// The components of an entity removed from a `World` by `World::take`
+///
+/// Can be passed to `World::spawn` or `World::insert`, including on a different world, to move
+/// the components there. Components that are never spawned are dropped along with this value.
+pub struct TakenEntity {
+    builder: EntityBuilder,
+}
+
+impl TakenEntity {
+    /// Take ownership of components of types `types`, in that order, as `move_out` supplies them
+    ///
+    /// # Safety
+    ///
+    /// `move_out` must pass a pointer to a valid value of each of `types` exactly once, in order,
+    /// and the values must not be used again afterwards.
+    pub(crate) unsafe fn new(
+        types: &[TypeInfo],
+        move_out: impl FnOnce(&mut dyn FnMut(*mut u8)),
+    ) -> Self {
+        let mut builder = EntityBuilder::new();
+        let mut types = types.iter();
+        move_out(&mut |ptr| builder.add_dynamic(*types.next().unwrap(), ptr));
+        builder.ids.extend(builder.info.iter().map(|x| x.0.id()));
+        Self { builder }
+    }
+
+    /// Borrow the `T` component, if present
+    pub fn get<T: Component>(&self) -> Option<&T> {
+        self.builder.get::<T>()
+    }
+}
+
+impl DynamicBundle for TakenEntity {
+    fn with_ids<T>(&self, f: impl FnOnce(&[TypeId]) -> T) -> T {
+        f(&self.builder.ids)
+    }
+
+    #[doc(hidden)]
+    fn type_info(&self) -> Vec<TypeInfo> {
+        self.builder.info.iter().map(|x| x.0).collect()
+    }
+
+    unsafe fn put(mut self, f: impl FnMut(*mut u8, TypeId, usize) -> bool) {
+        BuiltEntity {
+            builder: &mut self.builder,
+        }
+        .put(f)
+    }
+}
+
+/// Growable, suitably aligned storage for type-erased components packed end to end
+///
+/// Doesn't track what it holds; users must record each component's `TypeInfo` and offset in order
+/// to access or drop it.
+pub(crate) struct ComponentBuffer {
+    storage: NonNull<u8>,
+    layout: Layout,
+    cursor: usize,
+}
+
+impl ComponentBuffer {
+    pub(crate) fn new() -> Self {
+        Self {
+            storage: NonNull::dangling(),
+            layout: Layout::from_size_align(0, 1).unwrap(),
+            cursor: 0,
+        }
+    }
+
+    /// Move a component of type `ty` out of `component` into the buffer, returning its offset
+    ///
+    /// # Safety
+    ///
+    /// `component` must point to a valid value of type `ty`. Ownership of the value passes to the
+    /// buffer's user.
+    pub(crate) unsafe fn add_dynamic(&mut self, ty: TypeInfo, component: *mut u8) -> usize {
+        let offset = align(self.cursor, ty.layout().align());
+        let end = offset + ty.layout().size();
+        if end > self.layout.size() || ty.layout().align() > self.layout.align() {
+            self.grow(end, ty.layout().align());
+        }
+        ptr::copy_nonoverlapping(
+            component,
+            self.storage.as_ptr().add(offset),
+            ty.layout().size(),
+        );
+        self.cursor = end;
+        offset
+    }
+
+    /// Pointer to the component at `offset`
+    ///
+    /// # Safety
+    ///
+    /// `offset` must have been returned by `add_dynamic` since the last `clear`.
+    pub(crate) unsafe fn get(&self, offset: usize) -> *mut u8 {
+        self.storage.as_ptr().add(offset)
+    }
+
+    /// Forget all components, retaining the allocation for reuse
+    ///
+    /// Components must already have been dropped or moved out.
+    pub(crate) fn clear(&mut self) {
+        self.cursor = 0;
+    }
+
+    /// Move the storage into a new allocation of at least `min_size` bytes aligned to at least
+    /// `min_align`
+    fn grow(&mut self, min_size: usize, min_align: usize) {
+        let new_layout = Layout::from_size_align(
+            min_size.next_power_of_two().max(64),
+            self.layout.align().max(min_align),
+        )
+        .unwrap();
+        unsafe {
+            let new_storage = NonNull::new(alloc(new_layout)).unwrap();
+            ptr::copy_nonoverlapping(self.storage.as_ptr(), new_storage.as_ptr(), self.cursor);
+            if self.layout.size() != 0 {
+                dealloc(self.storage.as_ptr(), self.layout);
+            }
+            self.storage = new_storage;
+        }
+        self.layout = new_layout;
+    }
+}
+
+impl Drop for ComponentBuffer {
+    fn drop(&mut self) {
+        if self.layout.size() != 0 {
+            unsafe {
+                dealloc(self.storage.as_ptr(), self.layout);
+            }
+        }
+    }
+}
@@ -64,6 +64,7 @@ macro_rules! smaller_tuples_too {
 mod archetype;
 mod borrow;
 mod bundle;
+mod command_buffer;
 mod entities;
 mod entity_builder;
 mod query;
@@ -71,13 +72,20 @@ mod query_one;
 mod world;
 
 pub use archetype::Archetype;
-pub use borrow::{EntityRef, Ref, RefMut};
-pub use bundle::{Bundle, DynamicBundle, MissingComponent};
+pub use borrow::{ComponentsMut, EntityRef, Ref, RefMut};
+pub use bundle::{Bundle, CloneBundle, DynamicBundle, MissingComponent};
+pub use command_buffer::{CommandBuffer, SystemContext};
 pub use entities::{Entity, NoSuchEntity};
-pub use entity_builder::{BuiltEntity, EntityBuilder};
-pub use query::{Access, BatchedIter, Query, QueryBorrow, QueryIter, With, Without};
-pub use query_one::QueryOne;
-pub use world::{ArchetypesGeneration, Component, ComponentError, Iter, SpawnBatchIter, World};
+pub use entity_builder::{BuiltEntity, EntityBuilder, TakenEntity};
+pub use query::{
+    Access, BatchedIter, Copied, OrDefault, OrDefaultRef, Query, QueryBorrow, QueryIter, QueryMut,
+    QuerySingleError, WeakQuery, With, Without,
+};
+pub use query_one::{QueryOne, QueryOneError};
+pub use world::{
+    ArchetypeWriter, ArchetypesGeneration, Component, ComponentError, ComponentsMutIter, Iter,
+    SpawnBatchIter, World, WorldStats,
+};
 
 // Unstable implementation details needed by the macros
 #[doc(hidden)]
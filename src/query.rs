@@ -12,12 +12,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::alloc::sync::Arc;
+use crate::alloc::vec::{self, Vec};
+use core::fmt;
 use core::marker::PhantomData;
+use core::ops::Deref;
 use core::ptr::NonNull;
 
 use crate::archetype::Archetype;
 use crate::entities::EntityMeta;
-use crate::{Component, Entity};
+use crate::{Component, Entity, QueryOne, World};
 
 /// A collection of component types to fetch from a `World`
 pub trait Query {
@@ -64,7 +68,7 @@ pub enum Access {
     Write,
 }
 
-impl<'a, T: Component> Query for &'a T {
+impl<T: Component> Query for &'_ T {
     type Fetch = FetchRead<T>;
 }
 
@@ -101,7 +105,7 @@ impl<'a, T: Component> Fetch<'a> for FetchRead<T> {
     }
 }
 
-impl<'a, T: Component> Query for &'a mut T {
+impl<T: Component> Query for &'_ mut T {
     type Fetch = FetchWrite<T>;
 }
 
@@ -167,6 +171,151 @@ impl<'a, T: Fetch<'a>> Fetch<'a> for TryFetch<T> {
     }
 }
 
+/// Query element yielding a copy of a `T` component rather than a reference
+///
+/// Matches the same entities as `&T`, with the same shared borrow, but hands out values for
+/// convenient arithmetic on small `Copy` types.
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// let mut world = World::new();
+/// world.spawn((1u32,));
+/// world.spawn((2u32, true));
+/// let total = world.query::<Copied<u32>>().iter().map(|(_, x)| x).sum::<u32>();
+/// assert_eq!(total, 3);
+/// ```
+pub struct Copied<T>(PhantomData<fn() -> T>);
+
+impl<T: Component + Copy> Query for Copied<T> {
+    type Fetch = FetchCopied<T>;
+}
+
+#[doc(hidden)]
+pub struct FetchCopied<T>(FetchRead<T>);
+
+impl<'a, T: Component + Copy> Fetch<'a> for FetchCopied<T> {
+    type Item = T;
+
+    fn access(archetype: &Archetype) -> Option<Access> {
+        FetchRead::<T>::access(archetype)
+    }
+
+    fn borrow(archetype: &Archetype) {
+        FetchRead::<T>::borrow(archetype)
+    }
+    unsafe fn get(archetype: &'a Archetype, offset: usize) -> Option<Self> {
+        FetchRead::get(archetype, offset).map(Self)
+    }
+    fn release(archetype: &Archetype) {
+        FetchRead::<T>::release(archetype)
+    }
+
+    unsafe fn next(&mut self) -> T {
+        *self.0.next()
+    }
+}
+
+/// Query element yielding a `T` component, or a default for entities that lack one
+///
+/// Like `Option<&T>`, this does not restrict which entities match. Present components are
+/// borrowed, while entities lacking `T` share a single `T::default()` per archetype, so `T` need
+/// not be `Clone`.
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// let mut world = World::new();
+/// let a = world.spawn((123, 2.0f32));
+/// let b = world.spawn((456,));
+/// let entities = world.query::<(&i32, OrDefault<f32>)>()
+///     .iter()
+///     .map(|(e, (&i, f))| (e, i, *f))
+///     .collect::<Vec<_>>();
+/// assert_eq!(entities.len(), 2);
+/// assert!(entities.contains(&(a, 123, 2.0)));
+/// assert!(entities.contains(&(b, 456, 0.0)));
+/// ```
+pub struct OrDefault<T>(PhantomData<fn() -> T>);
+
+impl<T: Component + Default> Query for OrDefault<T> {
+    type Fetch = FetchOrDefault<T>;
+}
+
+/// Value yielded by `OrDefault<T>`, dereferencing to the `T` it stands for
+#[derive(Debug)]
+pub enum OrDefaultRef<'a, T> {
+    /// The entity's own component
+    Borrowed(&'a T),
+    /// The default shared by entities lacking `T`
+    Default(Arc<T>),
+}
+
+impl<T> Deref for OrDefaultRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match *self {
+            OrDefaultRef::Borrowed(x) => x,
+            OrDefaultRef::Default(ref x) => x,
+        }
+    }
+}
+
+impl<T> Clone for OrDefaultRef<'_, T> {
+    fn clone(&self) -> Self {
+        match *self {
+            OrDefaultRef::Borrowed(x) => OrDefaultRef::Borrowed(x),
+            OrDefaultRef::Default(ref x) => OrDefaultRef::Default(x.clone()),
+        }
+    }
+}
+
+#[doc(hidden)]
+pub enum FetchOrDefault<T> {
+    Present(NonNull<T>),
+    /// Constructed on first use, so archetypes with no entities never build a default
+    Absent(Option<Arc<T>>),
+}
+
+impl<'a, T: Component + Default> Fetch<'a> for FetchOrDefault<T> {
+    type Item = OrDefaultRef<'a, T>;
+
+    fn access(archetype: &Archetype) -> Option<Access> {
+        if archetype.has::<T>() {
+            Some(Access::Read)
+        } else {
+            Some(Access::Iterate)
+        }
+    }
+
+    fn borrow(archetype: &Archetype) {
+        archetype.borrow::<T>();
+    }
+    unsafe fn get(archetype: &'a Archetype, offset: usize) -> Option<Self> {
+        Some(match archetype.get::<T>() {
+            Some(x) => FetchOrDefault::Present(NonNull::new_unchecked(x.as_ptr().add(offset))),
+            None => FetchOrDefault::Absent(None),
+        })
+    }
+    fn release(archetype: &Archetype) {
+        archetype.release::<T>();
+    }
+
+    unsafe fn next(&mut self) -> OrDefaultRef<'a, T> {
+        match *self {
+            FetchOrDefault::Present(ref mut ptr) => {
+                let x = ptr.as_ptr();
+                *ptr = NonNull::new_unchecked(x.add(1));
+                OrDefaultRef::Borrowed(&*x)
+            }
+            FetchOrDefault::Absent(ref mut x) => {
+                OrDefaultRef::Default(x.get_or_insert_with(|| Arc::new(T::default())).clone())
+            }
+        }
+    }
+}
+
 /// Query transformer skipping entities that have a `T` component
 ///
 /// See also `QueryBorrow::without`.
@@ -302,18 +451,39 @@ impl<'w, Q: Query> QueryBorrow<'w, Q> {
     /// Execute the query
     ///
     /// Must be called only once per query.
-    pub fn iter<'q>(&'q mut self) -> QueryIter<'q, 'w, Q> {
+    pub fn iter<'q>(&'q mut self) -> QueryIter<'q, Q> {
         self.borrow();
-        QueryIter {
-            borrow: self,
-            archetype_index: 0,
-            iter: None,
-        }
+        unsafe { QueryIter::new(self.meta, self.archetypes) }
     }
 
     /// Like `iter`, but returns child iterators of at most `batch_size` elements
     ///
-    /// Useful for distributing work over a threadpool.
+    /// Useful for distributing work over a threadpool. Batches never span archetypes and are
+    /// `Send`, so they can be handed to any scheduler, e.g. through rayon's `par_bridge`. hecs
+    /// deliberately offers no parallel iterator of its own, leaving the choice of threadpool, and
+    /// the dependency on it, to the application. A query whose borrows conflict with each other,
+    /// e.g. `(&T, &mut T)`, still panics here as it would in `iter`.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// for i in 0..1000u32 {
+    ///     world.spawn((i, 0u64));
+    /// }
+    /// let mut query = world.query::<(&u32, &mut u64)>();
+    /// std::thread::scope(|scope| {
+    ///     for batch in query.iter_batched(256) {
+    ///         scope.spawn(move || {
+    ///             for (_, (&x, y)) in batch {
+    ///                 *y = u64::from(x) * 2;
+    ///             }
+    ///         });
+    ///     }
+    /// });
+    /// drop(query);
+    /// assert!(world.query::<(&u32, &u64)>().iter().all(|(_, (&x, &y))| y == u64::from(x) * 2));
+    /// ```
     pub fn iter_batched<'q>(&'q mut self, batch_size: u32) -> BatchedIter<'q, 'w, Q> {
         self.borrow();
         BatchedIter {
@@ -324,6 +494,87 @@ impl<'w, Q: Query> QueryBorrow<'w, Q> {
         }
     }
 
+    /// Execute a query expected to match exactly one entity
+    ///
+    /// Returns an error distinguishing whether no entities or several entities matched. Must be
+    /// called only once per query, like `iter`.
+    ///
+    /// Takes `&mut self` because it executes the query, exactly like `iter`, and for the same
+    /// reason there is no separate `single_mut`: a query containing `&mut T` yields `&mut T`.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// struct Camera;
+    /// let mut world = World::new();
+    /// world.spawn((Camera, 1.0f32));
+    /// world.spawn((2.0f32,));
+    /// let mut query = world.query::<&mut f32>().with::<Camera>();
+    /// let zoom = query.single().unwrap();
+    /// *zoom *= 2.0;
+    /// assert_eq!(*zoom, 2.0);
+    /// ```
+    pub fn single(&mut self) -> Result<<Q::Fetch as Fetch<'_>>::Item, QuerySingleError> {
+        let mut iter = self.iter();
+        match iter.len() {
+            0 => Err(QuerySingleError::NoEntities),
+            1 => Ok(iter.next().unwrap().1),
+            _ => Err(QuerySingleError::MultipleEntities),
+        }
+    }
+
+    /// Execute the query, returning the first entity for which `f` returns `true`
+    ///
+    /// Stops iterating as soon as a match is found. Must be called only once per query, like
+    /// `iter`.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((123, true));
+    /// let b = world.spawn((456, false));
+    /// assert_eq!(world.query::<&i32>().find(|_, &x| x > 200), Some(b));
+    /// assert_eq!(world.query::<&i32>().find(|_, &x| x > 500), None);
+    /// ```
+    pub fn find(
+        &mut self,
+        mut f: impl FnMut(Entity, <Q::Fetch as Fetch<'_>>::Item) -> bool,
+    ) -> Option<Entity> {
+        self.iter()
+            .find_map(|(entity, item)| if f(entity, item) { Some(entity) } else { None })
+    }
+
+    /// Collect the entities matching the query, sorted by id
+    ///
+    /// Does not borrow any components, so it won't conflict with component borrows held
+    /// elsewhere. Sorted output allows cheap set differences between, for example, the
+    /// selections of consecutive frames.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((123, true));
+    /// world.spawn((true,));
+    /// let b = world.spawn((456,));
+    /// assert_eq!(world.query::<&i32>().collect_entities_sorted(), [a, b]);
+    /// ```
+    pub fn collect_entities_sorted(&self) -> Vec<Entity> {
+        let mut entities = self
+            .archetypes
+            .iter()
+            .filter(|x| Q::Fetch::access(x).is_some())
+            .flat_map(|x| (0..x.len()).map(move |i| x.entity_id(i)))
+            .map(|id| Entity {
+                id,
+                generation: self.meta[id as usize].generation,
+            })
+            .collect::<Vec<_>>();
+        entities.sort_unstable_by_key(|x| x.id);
+        entities
+    }
+
     fn borrow(&mut self) {
         if self.borrowed {
             panic!(
@@ -418,24 +669,132 @@ impl<'w, Q: Query> Drop for QueryBorrow<'w, Q> {
 
 impl<'q, 'w, Q: Query> IntoIterator for &'q mut QueryBorrow<'w, Q> {
     type Item = (Entity, <Q::Fetch as Fetch<'q>>::Item);
-    type IntoIter = QueryIter<'q, 'w, Q>;
+    type IntoIter = QueryIter<'q, Q>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.iter()
     }
 }
 
+/// Error indicating that `QueryBorrow::single` did not match exactly one entity
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum QuerySingleError {
+    /// No entities matched the query
+    NoEntities,
+    /// More than one entity matched the query
+    MultipleEntities,
+}
+
+impl fmt::Display for QuerySingleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use QuerySingleError::*;
+        match *self {
+            NoEntities => f.write_str("no entities matched the query"),
+            MultipleEntities => f.write_str("multiple entities matched the query"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for QuerySingleError {}
+
+/// Query that borrows the world uniquely, needing no dynamic borrow checking
+///
+/// Obtained from `World::query_mut`.
+pub struct QueryMut<'q, Q: Query> {
+    meta: &'q [EntityMeta],
+    archetypes: &'q [Archetype],
+    _marker: PhantomData<Q>,
+}
+
+impl<'q, Q: Query> QueryMut<'q, Q> {
+    pub(crate) fn new(meta: &'q [EntityMeta], archetypes: &'q mut [Archetype]) -> Self {
+        // Unique access to the world rules out outside borrows, but `Q` itself may still alias,
+        // e.g. `(&mut T, &mut T)`. Borrowing and immediately releasing panics in that case.
+        for x in archetypes.iter() {
+            if Q::Fetch::access(x) >= Some(Access::Read) {
+                Q::Fetch::borrow(x);
+                Q::Fetch::release(x);
+            }
+        }
+        Self {
+            meta,
+            archetypes,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Iterate over the matching entities
+    ///
+    /// Unlike `QueryBorrow::iter`, this may be called any number of times.
+    pub fn iter(&mut self) -> QueryIter<'_, Q> {
+        unsafe { QueryIter::new(self.meta, self.archetypes) }
+    }
+
+    /// Transform the query into one that requires a certain component without borrowing it
+    ///
+    /// See `QueryBorrow::with`.
+    pub fn with<T: Component>(self) -> QueryMut<'q, With<T, Q>> {
+        QueryMut::new_unchecked(self.meta, self.archetypes)
+    }
+
+    /// Transform the query into one that skips entities having a certain component
+    ///
+    /// See `QueryBorrow::without`.
+    pub fn without<T: Component>(self) -> QueryMut<'q, Without<T, Q>> {
+        QueryMut::new_unchecked(self.meta, self.archetypes)
+    }
+
+    /// Construct without checking for aliasing, for queries known to access a subset of another
+    /// query's components
+    fn new_unchecked(meta: &'q [EntityMeta], archetypes: &'q [Archetype]) -> Self {
+        Self {
+            meta,
+            archetypes,
+            _marker: PhantomData,
+        }
+    }
+}
+
+unsafe impl<'q, Q: Query> Send for QueryMut<'q, Q> {}
+unsafe impl<'q, Q: Query> Sync for QueryMut<'q, Q> {}
+
+impl<'q, Q: Query> IntoIterator for QueryMut<'q, Q> {
+    type Item = (Entity, <Q::Fetch as Fetch<'q>>::Item);
+    type IntoIter = QueryIter<'q, Q>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        unsafe { QueryIter::new(self.meta, self.archetypes) }
+    }
+}
+
 /// Iterator over the set of entities with the components in `Q`
-pub struct QueryIter<'q, 'w, Q: Query> {
-    borrow: &'q mut QueryBorrow<'w, Q>,
+pub struct QueryIter<'q, Q: Query> {
+    meta: &'q [EntityMeta],
+    archetypes: &'q [Archetype],
     archetype_index: u32,
     iter: Option<ChunkIter<Q>>,
 }
 
-unsafe impl<'q, 'w, Q: Query> Send for QueryIter<'q, 'w, Q> {}
-unsafe impl<'q, 'w, Q: Query> Sync for QueryIter<'q, 'w, Q> {}
+impl<'q, Q: Query> QueryIter<'q, Q> {
+    /// # Safety
+    ///
+    /// `'q` must be sufficient to guarantee that `Q` cannot violate borrow safety, either with
+    /// dynamic borrow checks or by representing exclusive access to the `World`.
+    unsafe fn new(meta: &'q [EntityMeta], archetypes: &'q [Archetype]) -> Self {
+        Self {
+            meta,
+            archetypes,
+            archetype_index: 0,
+            iter: None,
+        }
+    }
+}
+
+unsafe impl<'q, Q: Query> Send for QueryIter<'q, Q> {}
+unsafe impl<'q, Q: Query> Sync for QueryIter<'q, Q> {}
 
-impl<'q, 'w, Q: Query> Iterator for QueryIter<'q, 'w, Q> {
+impl<'q, Q: Query> Iterator for QueryIter<'q, Q> {
     type Item = (Entity, <Q::Fetch as Fetch<'q>>::Item);
 
     #[inline]
@@ -443,7 +802,7 @@ impl<'q, 'w, Q: Query> Iterator for QueryIter<'q, 'w, Q> {
         loop {
             match self.iter {
                 None => {
-                    let archetype = self.borrow.archetypes.get(self.archetype_index as usize)?;
+                    let archetype = self.archetypes.get(self.archetype_index as usize)?;
                     self.archetype_index += 1;
                     unsafe {
                         self.iter = Q::Fetch::get(archetype, 0).map(|fetch| ChunkIter {
@@ -462,7 +821,7 @@ impl<'q, 'w, Q: Query> Iterator for QueryIter<'q, 'w, Q> {
                         return Some((
                             Entity {
                                 id,
-                                generation: self.borrow.meta[id as usize].generation,
+                                generation: self.meta[id as usize].generation,
                             },
                             components,
                         ));
@@ -478,14 +837,14 @@ impl<'q, 'w, Q: Query> Iterator for QueryIter<'q, 'w, Q> {
     }
 }
 
-impl<'q, 'w, Q: Query> ExactSizeIterator for QueryIter<'q, 'w, Q> {
+impl<'q, Q: Query> ExactSizeIterator for QueryIter<'q, Q> {
     fn len(&self) -> usize {
-        self.borrow
-            .archetypes
+        self.archetypes[self.archetype_index as usize..]
             .iter()
             .filter(|&x| Q::Fetch::access(x).is_some())
             .map(|x| x.len() as usize)
-            .sum()
+            .sum::<usize>()
+            + self.iter.as_ref().map_or(0, |x| x.len as usize)
     }
 }
 
@@ -583,6 +942,38 @@ impl<'q, 'w, Q: Query> Iterator for Batch<'q, 'w, Q> {
 unsafe impl<'q, 'w, Q: Query> Send for Batch<'q, 'w, Q> {}
 unsafe impl<'q, 'w, Q: Query> Sync for Batch<'q, 'w, Q> {}
 
+/// A snapshot of the entities matching `Q` that is revalidated at every step
+///
+/// Constructed by `World::weak_query`. Holds no borrow of the `World`, so the world may be
+/// modified between steps.
+pub struct WeakQuery<Q: Query> {
+    entities: vec::IntoIter<Entity>,
+    _marker: PhantomData<fn() -> Q>,
+}
+
+impl<Q: Query> WeakQuery<Q> {
+    pub(crate) fn new(entities: Vec<Entity>) -> Self {
+        Self {
+            entities: entities.into_iter(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Advance to the next entity that still exists and still satisfies `Q`
+    ///
+    /// Entities that were despawned, or that lost components required by `Q`, since the snapshot
+    /// was taken are skipped. Entities spawned since the snapshot was taken are never yielded.
+    pub fn next<'w>(&mut self, world: &'w World) -> Option<(Entity, QueryOne<'w, Q>)> {
+        for entity in &mut self.entities {
+            match world.query_one::<Q>(entity) {
+                Ok(query) if query.satisfied() => return Some((entity, query)),
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
 macro_rules! tuple_impl {
     ($($name: ident),*) => {
         impl<'a, $($name: Fetch<'a>),*> Fetch<'a> for ($($name,)*) {
@@ -610,6 +1001,7 @@ macro_rules! tuple_impl {
                 $($name::release(archetype);)*
             }
 
+            #[allow(clippy::unused_unit)]
             unsafe fn next(&mut self) -> Self::Item {
                 #[allow(non_snake_case)]
                 let ($($name,)*) = self;
@@ -1,7 +1,8 @@
+use core::fmt;
 use core::marker::PhantomData;
 
 use crate::query::{Fetch, With, Without};
-use crate::{Archetype, Component, Query};
+use crate::{Archetype, Component, NoSuchEntity, Query};
 
 /// A borrow of a `World` sufficient to execute the query `Q` on a single entity
 pub struct QueryOne<'a, Q: Query> {
@@ -26,6 +27,11 @@ impl<'a, Q: Query> QueryOne<'a, Q> {
         }
     }
 
+    /// Whether the entity satisfies the query
+    pub(crate) fn satisfied(&self) -> bool {
+        Q::Fetch::access(self.archetype).is_some()
+    }
+
     /// Get the query result, or `None` if the entity does not satisfy the query
     ///
     /// Must be called at most once.
@@ -82,3 +88,31 @@ impl<Q: Query> Drop for QueryOne<'_, Q> {
 
 unsafe impl<Q: Query> Send for QueryOne<'_, Q> {}
 unsafe impl<Q: Query> Sync for QueryOne<'_, Q> {}
+
+/// Error indicating why `World::query_one_mut` could not produce a result
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum QueryOneError {
+    /// The entity was already despawned
+    NoSuchEntity,
+    /// The entity exists but does not satisfy the query
+    Unsatisfied,
+}
+
+impl fmt::Display for QueryOneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use QueryOneError::*;
+        match *self {
+            NoSuchEntity => f.write_str("no such entity"),
+            Unsatisfied => f.write_str("entity does not satisfy the query"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for QueryOneError {}
+
+impl From<NoSuchEntity> for QueryOneError {
+    fn from(NoSuchEntity: NoSuchEntity) -> Self {
+        QueryOneError::NoSuchEntity
+    }
+}
@@ -15,6 +15,8 @@
 use crate::alloc::vec::Vec;
 use core::any::TypeId;
 use core::convert::TryFrom;
+use core::marker::PhantomData;
+use core::ops::ControlFlow;
 use core::{fmt, mem, ptr};
 
 #[cfg(feature = "std")]
@@ -22,11 +24,12 @@ use std::error::Error;
 
 use hashbrown::{HashMap, HashSet};
 
-use crate::archetype::Archetype;
-use crate::entities::{Entities, Location};
+use crate::archetype::{Archetype, TypeInfo};
+use crate::entities::{next_generation, Entities, Location};
 use crate::{
-    Bundle, DynamicBundle, Entity, EntityRef, MissingComponent, NoSuchEntity, Query, QueryBorrow,
-    QueryOne, Ref, RefMut,
+    Bundle, CloneBundle, CommandBuffer, ComponentsMut, DynamicBundle, Entity, EntityRef, Fetch,
+    MissingComponent, NoSuchEntity, Query, QueryBorrow, QueryMut, QueryOne, QueryOneError, Ref,
+    RefMut, TakenEntity, WeakQuery,
 };
 
 /// An unordered collection of entities, each having any number of distinctly typed components
@@ -47,8 +50,7 @@ impl World {
     /// Create an empty world
     pub fn new() -> Self {
         // `flush` assumes archetype 0 always exists, representing entities with no components.
-        let mut archetypes = Vec::new();
-        archetypes.push(Archetype::new(Vec::new()));
+        let archetypes = alloc::vec![Archetype::new(Vec::new())];
         let mut index = HashMap::default();
         index.insert(Vec::new(), 0);
         Self {
@@ -82,17 +84,93 @@ impl World {
         // necessary
         self.flush();
 
+        // Resolve the archetype first, so that an invalid bundle can't leak an entity ID
+        let archetype_id =
+            components.with_ids(|ids| self.archetype_for(ids, || components.type_info()));
         let entity = self.entities.alloc();
-        let archetype_id = components.with_ids(|ids| {
-            self.index.get(ids).copied().unwrap_or_else(|| {
-                let x = self.archetypes.len() as u32;
-                self.archetypes.push(Archetype::new(components.type_info()));
-                self.index.insert(ids.to_vec(), x);
-                self.archetype_generation += 1;
-                x
-            })
-        });
+        self.spawn_inner(archetype_id, entity, components);
+        entity
+    }
+
+    /// Create an entity with exactly the handle `entity`
+    ///
+    /// Useful for recreating entities when deserializing a world or replicating one from a
+    /// server. Any live entity that shares `entity`'s ID, whatever its generation, is despawned
+    /// first. Cost grows with the number of free IDs, so prefer `spawn` where possible.
+    ///
+    /// Metadata is allocated for every ID up to `entity`'s, so a large ID is expensive even in an
+    /// otherwise empty world. Panics if the ID is `u32::MAX`.
+    ///
+    /// hecs has no built-in snapshot format, since only the application knows which component
+    /// types to save and how to encode them. Instead, a world can be saved in any format by
+    /// visiting `World::iter` and recording each `Entity::to_bits` alongside the components of
+    /// interest, then restored by rebuilding each entity with an `EntityBuilder` and this method,
+    /// so that saved handles remain valid.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// struct Name(String);
+    ///
+    /// let mut world = World::new();
+    /// let a = world.spawn((Name("a".into()), 1u32));
+    /// let b = world.spawn((Name("b".into()),));
+    ///
+    /// let saved = world
+    ///     .iter()
+    ///     .map(|(id, e)| {
+    ///         let name = e.get::<Name>().map(|x| x.0.clone());
+    ///         (id.to_bits(), name, e.get::<u32>().map(|x| *x))
+    ///     })
+    ///     .collect::<Vec<_>>();
+    ///
+    /// let mut restored = World::new();
+    /// let mut builder = EntityBuilder::new();
+    /// for (bits, name, value) in saved {
+    ///     if let Some(name) = name {
+    ///         builder.add(Name(name));
+    ///     }
+    ///     if let Some(value) = value {
+    ///         builder.add(value);
+    ///     }
+    ///     restored.spawn_at(Entity::from_bits(bits).unwrap(), builder.build());
+    /// }
+    /// assert_eq!(restored.get::<Name>(a).unwrap().0, "a");
+    /// assert_eq!(*restored.get::<u32>(a).unwrap(), 1);
+    /// assert_eq!(restored.get::<Name>(b).unwrap().0, "b");
+    /// assert!(restored.get::<u32>(b).is_err());
+    /// ```
+    pub fn spawn_at(&mut self, entity: Entity, components: impl DynamicBundle) {
+        self.flush();
+        let archetype_id =
+            components.with_ids(|ids| self.archetype_for(ids, || components.type_info()));
+        if let Some(loc) = self.entities.alloc_at(entity) {
+            if let Some(moved) =
+                unsafe { self.archetypes[loc.archetype as usize].remove(loc.index) }
+            {
+                self.entities.meta[moved as usize].location.index = loc.index;
+            }
+        }
+        self.spawn_inner(archetype_id, entity, components);
+    }
+
+    /// Find the archetype for entities with exactly the components `ids`, creating it with the
+    /// components described by `info` if necessary
+    ///
+    /// `ids` must be sorted in the same order as `TypeInfo`.
+    fn archetype_for(&mut self, ids: &[TypeId], info: impl FnOnce() -> Vec<TypeInfo>) -> u32 {
+        if let Some(&x) = self.index.get(ids) {
+            return x;
+        }
+        let x = self.archetypes.len() as u32;
+        self.archetypes.push(Archetype::new(info()));
+        self.index.insert(ids.to_vec(), x);
+        self.archetype_generation += 1;
+        x
+    }
 
+    /// Store `components` for the freshly allocated `entity` in the archetype `archetype_id`
+    fn spawn_inner(&mut self, archetype_id: u32, entity: Entity, components: impl DynamicBundle) {
         let archetype = &mut self.archetypes[archetype_id as usize];
         unsafe {
             let index = archetype.allocate(entity.id);
@@ -105,7 +183,25 @@ impl World {
                 index,
             };
         }
-        entity
+    }
+
+    /// Create an entity with clones of borrowed components
+    ///
+    /// Equivalent to calling `spawn` with a tuple of `clone`d values, convenient when spawning
+    /// entities from borrowed templates.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let (name, level) = (String::from("goblin"), 3);
+    /// let a = world.spawn_cloned((&name, &level));
+    /// let b = world.spawn_cloned((&name, &level));
+    /// assert_eq!(*world.get::<String>(a).unwrap(), "goblin");
+    /// assert_eq!(*world.get::<i32>(b).unwrap(), 3);
+    /// ```
+    pub fn spawn_cloned(&mut self, components: impl CloneBundle) -> Entity {
+        self.spawn(components.clone_bundle())
     }
 
     /// Efficiently spawn a large number of entities with the same components
@@ -168,44 +264,282 @@ impl World {
         Ok(())
     }
 
+    /// Despawn every entity matching `Q` for which `f` returns `false`
+    ///
+    /// Entities that don't match `Q` are always retained. Despawned entities' components are
+    /// dropped and their IDs become available for reuse, exactly as with `despawn`.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((1, "a"));
+    /// let b = world.spawn((2, "b"));
+    /// let c = world.spawn(("c",));
+    /// world.retain::<&i32>(|_, &x| x % 2 == 0);
+    /// assert!(!world.contains(a));
+    /// assert!(world.contains(b));
+    /// assert!(world.contains(c));
+    /// ```
+    pub fn retain<Q: Query>(
+        &mut self,
+        mut f: impl for<'a> FnMut(Entity, <Q::Fetch as Fetch<'a>>::Item) -> bool,
+    ) {
+        let doomed = self
+            .query_mut::<Q>()
+            .into_iter()
+            .filter_map(|(entity, item)| if f(entity, item) { None } else { Some(entity) })
+            .collect::<Vec<_>>();
+        for entity in doomed {
+            self.despawn(entity).unwrap();
+        }
+    }
+
+    /// Despawn `entity`, moving all of its components out for spawning elsewhere
+    ///
+    /// Useful for migrating entities between worlds, e.g. when streaming parts of a large map in
+    /// and out. Components are moved, never cloned.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut a = World::new();
+    /// let mut b = World::new();
+    /// let e = a.spawn((123, "abc"));
+    /// let taken = a.take(e).unwrap();
+    /// assert!(!a.contains(e));
+    /// let e = b.spawn(taken);
+    /// assert_eq!(*b.get::<i32>(e).unwrap(), 123);
+    /// assert_eq!(*b.get::<&str>(e).unwrap(), "abc");
+    /// ```
+    pub fn take(&mut self, entity: Entity) -> Result<TakenEntity, NoSuchEntity> {
+        self.flush();
+        let loc = self.entities.free(entity)?;
+        let archetype = &mut self.archetypes[loc.archetype as usize];
+        let types = archetype.types().to_vec();
+        let mut moved = None;
+        let taken = unsafe {
+            TakenEntity::new(&types, |f| {
+                moved = archetype.move_to(loc.index, |ptr, _, _| f(ptr));
+            })
+        };
+        if let Some(moved) = moved {
+            self.entities.meta[moved as usize].location.index = loc.index;
+        }
+        Ok(taken)
+    }
+
+    /// Destroy many entities, moving their `T` components out instead of dropping them
+    ///
+    /// Useful for recycling resources held by components, such as in an object pool. Every live
+    /// entity in `entities` is despawned; those lacking any component in `T` have all their
+    /// components dropped and contribute nothing to the result. Entities that no longer exist are
+    /// skipped.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((vec![1, 2, 3], true));
+    /// let b = world.spawn((true,));
+    /// let taken = world.despawn_batch_take::<(Vec<i32>,)>(vec![a, b]);
+    /// assert_eq!(taken, vec![(vec![1, 2, 3],)]);
+    /// assert!(!world.contains(a));
+    /// assert!(!world.contains(b));
+    /// ```
+    pub fn despawn_batch_take<T: Bundle>(
+        &mut self,
+        entities: impl IntoIterator<Item = Entity>,
+    ) -> Vec<T> {
+        self.flush();
+        let kept = T::with_static_ids(|ids| ids.iter().copied().collect::<HashSet<_>>());
+        let mut taken = Vec::new();
+        for entity in entities {
+            let loc = match self.entities.get_mut(entity) {
+                Ok(x) => *x,
+                Err(_) => continue,
+            };
+            let archetype = &mut self.archetypes[loc.archetype as usize];
+            let bundle =
+                match unsafe { T::get(|ty, size| archetype.get_dynamic(ty, size, loc.index)) } {
+                    Ok(x) => x,
+                    Err(_) => {
+                        self.despawn(entity).unwrap();
+                        continue;
+                    }
+                };
+            self.entities.free(entity).unwrap();
+            unsafe {
+                // `bundle` now owns the `T` components, so only the rest are dropped
+                for ty in archetype.types() {
+                    if !kept.contains(&ty.id()) {
+                        let ptr = archetype.get_dynamic(ty.id(), ty.layout().size(), loc.index);
+                        ty.drop(ptr.unwrap().as_ptr());
+                    }
+                }
+                if let Some(moved) = archetype.move_to(loc.index, |_, _, _| {}) {
+                    self.entities.meta[moved as usize].location.index = loc.index;
+                }
+            }
+            taken.push(bundle);
+        }
+        taken
+    }
+
+    /// Move `entity` to the front of the storage for its set of components
+    ///
+    /// The entity previously at the front takes `entity`'s old position. Entities at the front of
+    /// their storage are visited first by queries, so promoting frequently accessed entities can
+    /// improve cache locality for systems that only process part of a query.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// world.spawn((1,));
+    /// let b = world.spawn((2,));
+    /// world.promote(b).unwrap();
+    /// assert_eq!(world.query::<&i32>().iter().next().unwrap().0, b);
+    /// ```
+    pub fn promote(&mut self, entity: Entity) -> Result<(), NoSuchEntity> {
+        self.flush();
+        let loc = *self.entities.get_mut(entity)?;
+        if loc.index == 0 {
+            return Ok(());
+        }
+        let archetype = &mut self.archetypes[loc.archetype as usize];
+        let front = archetype.entity_id(0);
+        unsafe {
+            archetype.swap(0, loc.index);
+        }
+        self.entities.meta[front as usize].location.index = loc.index;
+        self.entities.meta[entity.id as usize].location.index = 0;
+        Ok(())
+    }
+
     /// Ensure `additional` entities with exact components `T` can be spawned without reallocating
     pub fn reserve<T: Bundle>(&mut self, additional: u32) {
         self.reserve_inner::<T>(additional);
     }
 
+    /// Release unused capacity held for entities with exact components `T`
+    ///
+    /// Storage for other combinations of components is unaffected, so this can return the memory
+    /// used by a temporary spike in one kind of entity without disturbing the rest of the world.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let particles = world.spawn_batch((0..1000).map(|i| (i,))).collect::<Vec<_>>();
+    /// for e in particles {
+    ///     world.despawn(e).unwrap();
+    /// }
+    /// world.shrink_archetype_for::<(i32,)>();
+    /// assert!(world.archetypes().all(|x| x.capacity() == 0));
+    /// ```
+    pub fn shrink_archetype_for<T: Bundle>(&mut self) {
+        self.flush();
+        if let Some(&x) = T::with_static_ids(|ids| self.index.get(ids)) {
+            self.archetypes[x as usize].shrink_to_fit();
+        }
+    }
+
     fn reserve_inner<T: Bundle>(&mut self, additional: u32) -> u32 {
         self.flush();
         self.entities.reserve(additional);
 
-        let archetype_id = T::with_static_ids(|ids| {
-            self.index.get(ids).copied().unwrap_or_else(|| {
-                let x = self.archetypes.len() as u32;
-                self.archetypes.push(Archetype::new(T::static_type_info()));
-                self.index.insert(ids.to_vec(), x);
-                self.archetype_generation += 1;
-                x
-            })
-        });
-
+        let archetype_id = T::with_static_ids(|ids| self.archetype_for(ids, T::static_type_info));
         self.archetypes[archetype_id as usize].reserve(additional);
         archetype_id
     }
 
+    /// Resolve the storage for entities with exact components `T` once, for repeated spawning
+    ///
+    /// Spawning through the returned `ArchetypeWriter` skips the per-spawn archetype lookup
+    /// performed by `spawn`, which can help tight loops where `spawn_batch` is inconvenient.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let writer = world.prepare_spawn::<(i32, bool)>();
+    /// let a = writer.spawn(&mut world, (123, true));
+    /// let b = writer.spawn(&mut world, (456, false));
+    /// assert_eq!(*world.get::<i32>(a).unwrap(), 123);
+    /// assert_eq!(*world.get::<bool>(b).unwrap(), false);
+    /// ```
+    pub fn prepare_spawn<T: Bundle>(&mut self) -> ArchetypeWriter<T> {
+        ArchetypeWriter {
+            archetype_id: T::with_static_ids(|ids| self.archetype_for(ids, T::static_type_info)),
+            _marker: PhantomData,
+        }
+    }
+
     /// Despawn all entities
     ///
-    /// Preserves allocated storage for reuse.
+    /// Preserves allocated storage for reuse. Handles to the despawned entities are invalidated,
+    /// just as if each had been passed to `despawn`.
     pub fn clear(&mut self) {
+        self.flush();
         for x in &mut self.archetypes {
+            for index in 0..x.len() {
+                let meta = &mut self.entities.meta[x.entity_id(index) as usize];
+                meta.generation = next_generation(meta.generation);
+            }
             x.clear();
         }
         self.entities.clear();
     }
 
+    /// Number of currently live entities
+    ///
+    /// Includes entities reserved with `reserve_entity` that have not yet been flushed.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// assert!(world.is_empty());
+    /// let a = world.spawn((123,));
+    /// world.spawn(());
+    /// world.despawn(a).unwrap();
+    /// assert_eq!(world.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.entities.len() as usize
+    }
+
+    /// Whether no entities are live
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     /// Whether `entity` still exists
     pub fn contains(&self, entity: Entity) -> bool {
         self.entities.contains(entity)
     }
 
+    /// Whether `entity` exists and has a `T` component
+    ///
+    /// Returns `false` for stale handles rather than an error, and borrows nothing.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let e = world.spawn((123,));
+    /// assert!(world.has::<i32>(e));
+    /// assert!(!world.has::<bool>(e));
+    /// world.despawn(e).unwrap();
+    /// assert!(!world.has::<i32>(e));
+    /// ```
+    pub fn has<T: Component>(&self, entity: Entity) -> bool {
+        self.entities
+            .get(entity)
+            .is_ok_and(|loc| self.archetypes[loc.archetype as usize].has::<T>())
+    }
+
     /// Efficiently iterate over all entities that have certain components
     ///
     /// Calling `iter` on the returned value yields `(Entity, Q)` tuples, where `Q` is some query
@@ -249,6 +583,129 @@ impl World {
         QueryBorrow::new(&self.entities.meta, &self.archetypes)
     }
 
+    /// Query a uniquely borrowed world
+    ///
+    /// Like `query`, but faster because dynamic borrow checks can be skipped, and the result can
+    /// be iterated any number of times. Panics if `Q` itself is illegal, e.g. `(&mut T, &mut T)`.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((123, true));
+    /// let b = world.spawn((456,));
+    /// for (_, (x, flag)) in world.query_mut::<(&mut i32, Option<&bool>)>() {
+    ///     if flag.is_some() {
+    ///         *x += 1;
+    ///     }
+    /// }
+    /// assert_eq!(*world.get::<i32>(a).unwrap(), 124);
+    /// assert_eq!(*world.get::<i32>(b).unwrap(), 456);
+    /// ```
+    pub fn query_mut<Q: Query>(&mut self) -> QueryMut<'_, Q> {
+        QueryMut::new(&self.entities.meta, &mut self.archetypes)
+    }
+
+    /// Fold over all entities matching `Q`, stopping early if `f` returns `ControlFlow::Break`
+    ///
+    /// Returns the value carried by the `Break`, or the final accumulator if every entity was
+    /// visited. Handy for searches that can finish before examining the whole world.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// # use core::ops::ControlFlow;
+    /// let mut world = World::new();
+    /// world.spawn_batch((0..10).map(|i| (i,)));
+    /// // Sum values until the running total exceeds 10
+    /// let total = world.query_fold::<&i32, _>(0, |acc, _, &x| {
+    ///     let acc = acc + x;
+    ///     if acc > 10 { ControlFlow::Break(acc) } else { ControlFlow::Continue(acc) }
+    /// });
+    /// assert!(total > 10 && total < 45);
+    /// ```
+    pub fn query_fold<Q: Query, B>(
+        &self,
+        init: B,
+        mut f: impl for<'q> FnMut(B, Entity, <Q::Fetch as Fetch<'q>>::Item) -> ControlFlow<B, B>,
+    ) -> B {
+        match self
+            .query::<Q>()
+            .iter()
+            .try_fold(init, |acc, (entity, item)| f(acc, entity, item))
+        {
+            ControlFlow::Continue(x) | ControlFlow::Break(x) => x,
+        }
+    }
+
+    /// Iterate over all entities that have at least one component whose type is in `ids`
+    ///
+    /// Useful when the set of interesting component types is only known at runtime. No components
+    /// are borrowed. Entities are yielded in arbitrary order.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// # use std::any::TypeId;
+    /// let mut world = World::new();
+    /// let a = world.spawn((123, "abc"));
+    /// let b = world.spawn((true,));
+    /// let c = world.spawn((1.0f32,));
+    /// let ids = [TypeId::of::<i32>(), TypeId::of::<bool>()];
+    /// let entities = world.query_any_of(&ids).collect::<Vec<_>>();
+    /// assert_eq!(entities.len(), 2);
+    /// assert!(entities.contains(&a));
+    /// assert!(entities.contains(&b));
+    /// ```
+    pub fn query_any_of<'a>(&'a self, ids: &'a [TypeId]) -> impl Iterator<Item = Entity> + 'a {
+        let meta = &self.entities.meta;
+        self.archetypes
+            .iter()
+            .filter(move |archetype| ids.iter().any(|&id| archetype.has_dynamic(id)))
+            .flat_map(move |archetype| {
+                (0..archetype.len()).map(move |index| {
+                    let id = archetype.entity_id(index);
+                    Entity {
+                        id,
+                        generation: meta[id as usize].generation,
+                    }
+                })
+            })
+    }
+
+    /// Snapshot the entities matching `Q` for iteration that tolerates changes to the world
+    ///
+    /// Unlike `query`, the returned `WeakQuery` does not borrow the world. Each step revalidates
+    /// the next entity's generation and components, skipping any that were despawned or no longer
+    /// satisfy `Q`. This makes it safe to hold across points where the world might be modified, at
+    /// the cost of a lookup per entity; prefer `query` whenever the world is not modified during
+    /// iteration.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((123,));
+    /// let b = world.spawn((456,));
+    /// let mut weak = world.weak_query::<&i32>();
+    /// let (first, _) = weak.next(&world).unwrap();
+    /// world.despawn(if first == a { b } else { a }).unwrap();
+    /// assert!(weak.next(&world).is_none());
+    /// ```
+    pub fn weak_query<Q: Query>(&self) -> WeakQuery<Q> {
+        let mut entities = Vec::new();
+        for archetype in self.archetypes.iter().filter(|x| x.access::<Q>().is_some()) {
+            entities.extend((0..archetype.len()).map(|index| {
+                let id = archetype.entity_id(index);
+                Entity {
+                    id,
+                    generation: self.entities.meta[id as usize].generation,
+                }
+            }));
+        }
+        WeakQuery::new(entities)
+    }
+
     /// Prepare a query against a single entity
     ///
     /// Call `get` on the resulting `QueryOne` to actually execute the query. The `QueryOne` value
@@ -273,6 +730,44 @@ impl World {
         Ok(unsafe { QueryOne::new(&self.archetypes[loc.archetype as usize], loc.index) })
     }
 
+    /// Query a single entity in a uniquely borrowed world
+    ///
+    /// Like `query_one`, but faster because dynamic borrow checks can be skipped, and the results
+    /// borrow only the world rather than an intermediate `QueryOne`. Panics if `Q` itself is
+    /// illegal, e.g. `(&mut T, &mut T)`.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((123, true, "abc"));
+    /// let (number, flag) = world.query_one_mut::<(&mut i32, &bool)>(a).unwrap();
+    /// if *flag { *number *= 2; }
+    /// assert_eq!(*number, 246);
+    /// assert_eq!(
+    ///     world.query_one_mut::<&f32>(a).unwrap_err(),
+    ///     QueryOneError::Unsatisfied
+    /// );
+    /// ```
+    pub fn query_one_mut<Q: Query>(
+        &mut self,
+        entity: Entity,
+    ) -> Result<<Q::Fetch as Fetch<'_>>::Item, QueryOneError> {
+        let loc = self.entities.get(entity)?;
+        let archetype = &self.archetypes[loc.archetype as usize];
+        if Q::Fetch::access(archetype).is_none() {
+            return Err(QueryOneError::Unsatisfied);
+        }
+        // Unique access to the world rules out outside borrows, but `Q` may still alias itself.
+        Q::Fetch::borrow(archetype);
+        Q::Fetch::release(archetype);
+        unsafe {
+            let mut fetch =
+                Q::Fetch::get(archetype, loc.index as usize).ok_or(QueryOneError::Unsatisfied)?;
+            Ok(fetch.next())
+        }
+    }
+
     /// Borrow the `T` component of `entity`
     ///
     /// Panics if the component is already uniquely borrowed from another entity with the same
@@ -296,6 +791,45 @@ impl World {
         Ok(unsafe { RefMut::new(&self.archetypes[loc.archetype as usize], loc.index)? })
     }
 
+    /// Uniquely borrow the `T` components of several distinct entities at once
+    ///
+    /// Unlike `get_mut`, this works even if the entities share an archetype, since exclusive
+    /// access to the world rules out any other borrows. Each result is `None` if that entity does
+    /// not exist or has no `T`. Panics if the same entity appears more than once.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((10,));
+    /// let b = world.spawn((20,));
+    /// let [x, y] = world.get_many_mut::<i32, 2>([a, b]);
+    /// let (x, y) = (x.unwrap(), y.unwrap());
+    /// *x -= 5;
+    /// *y += 5;
+    /// assert_eq!(*world.get::<i32>(a).unwrap(), 5);
+    /// assert_eq!(*world.get::<i32>(b).unwrap(), 25);
+    /// ```
+    pub fn get_many_mut<T: Component, const N: usize>(
+        &mut self,
+        entities: [Entity; N],
+    ) -> [Option<&mut T>; N] {
+        for (i, a) in entities.iter().enumerate() {
+            if entities[..i].contains(a) {
+                panic!("get_many_mut called with duplicate entity {:?}", a);
+            }
+        }
+        let archetypes = &self.archetypes;
+        let meta = &self.entities;
+        core::array::from_fn(|i| {
+            let loc = meta.get(entities[i]).ok()?;
+            let column = archetypes[loc.archetype as usize].get::<T>()?;
+            // Safe because the entities are distinct, so the references are disjoint, and `&mut
+            // self` rules out any other outstanding borrows.
+            unsafe { Some(&mut *column.as_ptr().add(loc.index as usize)) }
+        })
+    }
+
     /// Access an entity regardless of its component types
     ///
     /// Does not immediately borrow any component.
@@ -306,6 +840,59 @@ impl World {
         })
     }
 
+    /// Uniquely access an entity's components without knowing their types
+    ///
+    /// Requires unique access to the world, ensuring that no other borrows of the entity's
+    /// components can be live.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// # use std::any::TypeId;
+    /// let mut world = World::new();
+    /// let e = world.spawn((123u32, "abc"));
+    /// let mut components = world.components_mut(e).unwrap();
+    /// let ptr = components.get_mut_by_id(TypeId::of::<u32>()).unwrap();
+    /// unsafe { *ptr.cast::<u32>() = 456; }
+    /// assert_eq!(*world.get::<u32>(e).unwrap(), 456);
+    /// ```
+    pub fn components_mut(&mut self, entity: Entity) -> Result<ComponentsMut<'_>, NoSuchEntity> {
+        self.flush();
+        Ok(match self.entities.get(entity)? {
+            Location { archetype: 0, .. } => ComponentsMut::empty(),
+            loc => unsafe {
+                ComponentsMut::new(&self.archetypes[loc.archetype as usize], loc.index)
+            },
+        })
+    }
+
+    /// Uniquely access every entity's components without knowing their types
+    ///
+    /// The type-erased counterpart of `query_mut`, visiting every entity. Each entity's
+    /// `ComponentsMut` is independent of the others', so any number may be held at once.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// # use std::any::TypeId;
+    /// let mut world = World::new();
+    /// let a = world.spawn((1u32, "abc"));
+    /// let b = world.spawn((2u32,));
+    /// for (_, mut components) in world.query_components_mut() {
+    ///     if let Some(ptr) = components.get_mut_by_id(TypeId::of::<u32>()) {
+    ///         unsafe { *ptr.cast::<u32>() *= 10; }
+    ///     }
+    /// }
+    /// assert_eq!(*world.get::<u32>(a).unwrap(), 10);
+    /// assert_eq!(*world.get::<u32>(b).unwrap(), 20);
+    /// ```
+    pub fn query_components_mut(&mut self) -> ComponentsMutIter<'_> {
+        self.flush();
+        ComponentsMutIter {
+            inner: Iter::new(&self.archetypes, &self.entities),
+        }
+    }
+
     /// Iterate over all entities in the world
     ///
     /// Entities are yielded in arbitrary order. Prefer `World::query` for better performance when
@@ -326,6 +913,27 @@ impl World {
         Iter::new(&self.archetypes, &self.entities)
     }
 
+    /// Iterate over the handles of all entities in the world
+    ///
+    /// Like `iter`, but yields only the `Entity`, for use with accessors like `get`. Entities
+    /// reserved with `reserve_entity` are not visited until the world is next modified.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((1,));
+    /// let b = world.spawn((2,));
+    /// world.despawn(a).unwrap();
+    /// for entity in world.iter_entities() {
+    ///     assert_eq!(entity, b);
+    ///     assert_eq!(*world.get::<i32>(entity).unwrap(), 2);
+    /// }
+    /// ```
+    pub fn iter_entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.iter().map(|(id, _)| id)
+    }
+
     /// Add `components` to `entity`
     ///
     /// Computational cost is proportional to the number of components `entity` has. If an entity
@@ -347,10 +955,8 @@ impl World {
         entity: Entity,
         components: impl DynamicBundle,
     ) -> Result<(), NoSuchEntity> {
-        use hashbrown::hash_map::Entry;
-
         self.flush();
-        let loc = self.entities.get_mut(entity)?;
+        let loc = *self.entities.get_mut(entity)?;
         unsafe {
             // Assemble Vec<TypeInfo> for the final entity
             let arch = &mut self.archetypes[loc.archetype as usize];
@@ -366,16 +972,7 @@ impl World {
 
             // Find the archetype it'll live in
             let elements = info.iter().map(|x| x.id()).collect::<Vec<_>>();
-            let target = match self.index.entry(elements) {
-                Entry::Occupied(x) => *x.get(),
-                Entry::Vacant(x) => {
-                    let index = self.archetypes.len() as u32;
-                    self.archetypes.push(Archetype::new(info));
-                    x.insert(index);
-                    self.archetype_generation += 1;
-                    index
-                }
-            };
+            let target = self.archetype_for(&elements, || info);
 
             if target == loc.archetype {
                 // Update components in the current archetype
@@ -394,8 +991,11 @@ impl World {
                 target as usize,
             );
             let target_index = target_arch.allocate(entity.id);
-            loc.archetype = target;
-            let old_index = mem::replace(&mut loc.index, target_index);
+            let old_index = loc.index;
+            self.entities.meta[entity.id as usize].location = Location {
+                archetype: target,
+                index: target_index,
+            };
             if let Some(moved) = source_arch.move_to(old_index, |ptr, ty, size| {
                 target_arch.put_dynamic(ptr, ty, size, target_index);
             }) {
@@ -420,6 +1020,91 @@ impl World {
         self.insert(entity, (component,))
     }
 
+    /// Add `T` to every entity in `entities`, taking each entity's value from `values`
+    ///
+    /// The destination archetype is resolved once per distinct source archetype rather than once
+    /// per entity, which makes this cheaper than calling `insert_one` in a loop when applying the
+    /// same kind of component to a group. Each entity is still moved to its destination
+    /// individually; only the lookup is shared. Entities which already have a `T` have it
+    /// replaced. If
+    /// `values` runs out early, the remaining entities are left unchanged.
+    ///
+    /// Returns `Err(NoSuchEntity)` without modifying the world if any entity does not exist.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((123,));
+    /// let b = world.spawn((456, "abc"));
+    /// world.insert_batch(&[a, b], vec![true, false]).unwrap();
+    /// assert_eq!(*world.get::<bool>(a).unwrap(), true);
+    /// assert_eq!(*world.get::<bool>(b).unwrap(), false);
+    /// ```
+    pub fn insert_batch<T: Component>(
+        &mut self,
+        entities: &[Entity],
+        values: impl IntoIterator<Item = T>,
+    ) -> Result<(), NoSuchEntity> {
+        self.flush();
+        if !entities.iter().all(|&e| self.contains(e)) {
+            return Err(NoSuchEntity);
+        }
+        let ty = TypeInfo::of::<T>();
+        let mut targets = HashMap::<u32, u32>::default();
+        for (&entity, value) in entities.iter().zip(values) {
+            let loc = *self.entities.get_mut(entity).unwrap();
+            let target = match targets.get(&loc.archetype) {
+                Some(&x) => x,
+                None => {
+                    let arch = &self.archetypes[loc.archetype as usize];
+                    let x = if arch.has_dynamic(ty.id()) {
+                        loc.archetype
+                    } else {
+                        let mut info = arch.types().to_vec();
+                        info.push(ty);
+                        info.sort();
+                        let elements = info.iter().map(|x| x.id()).collect::<Vec<_>>();
+                        self.archetype_for(&elements, || info)
+                    };
+                    targets.insert(loc.archetype, x);
+                    x
+                }
+            };
+            let mut value = mem::ManuallyDrop::new(value);
+            let ptr = (&mut *value as *mut T).cast::<u8>();
+            unsafe {
+                if target == loc.archetype {
+                    let arch = &mut self.archetypes[target as usize];
+                    let old = arch
+                        .get_dynamic(ty.id(), ty.layout().size(), loc.index)
+                        .unwrap();
+                    ty.drop(old.as_ptr());
+                    arch.put_dynamic(ptr, ty.id(), ty.layout().size(), loc.index);
+                    continue;
+                }
+
+                let (source_arch, target_arch) = index2(
+                    &mut self.archetypes,
+                    loc.archetype as usize,
+                    target as usize,
+                );
+                let target_index = target_arch.allocate(entity.id);
+                if let Some(moved) = source_arch.move_to(loc.index, |src, id, size| {
+                    target_arch.put_dynamic(src, id, size, target_index);
+                }) {
+                    self.entities.meta[moved as usize].location.index = loc.index;
+                }
+                target_arch.put_dynamic(ptr, ty.id(), ty.layout().size(), target_index);
+                self.entities.meta[entity.id as usize].location = Location {
+                    archetype: target,
+                    index: target_index,
+                };
+            }
+        }
+        Ok(())
+    }
+
     /// Remove components from `entity`
     ///
     /// Computational cost is proportional to the number of components `entity` has. The entity
@@ -440,10 +1125,8 @@ impl World {
     /// assert_eq!(*world.get::<bool>(e).unwrap(), true);
     /// ```
     pub fn remove<T: Bundle>(&mut self, entity: Entity) -> Result<T, ComponentError> {
-        use hashbrown::hash_map::Entry;
-
         self.flush();
-        let loc = self.entities.get_mut(entity)?;
+        let loc = *self.entities.get_mut(entity)?;
         unsafe {
             let removed = T::with_static_ids(|ids| ids.iter().copied().collect::<HashSet<_>>());
             let info = self.archetypes[loc.archetype as usize]
@@ -453,16 +1136,7 @@ impl World {
                 .filter(|x| !removed.contains(&x.id()))
                 .collect::<Vec<_>>();
             let elements = info.iter().map(|x| x.id()).collect::<Vec<_>>();
-            let target = match self.index.entry(elements) {
-                Entry::Occupied(x) => *x.get(),
-                Entry::Vacant(x) => {
-                    self.archetypes.push(Archetype::new(info));
-                    let index = (self.archetypes.len() - 1) as u32;
-                    x.insert(index);
-                    self.archetype_generation += 1;
-                    index
-                }
-            };
+            let target = self.archetype_for(&elements, || info);
             let old_index = loc.index;
             let source_arch = &self.archetypes[loc.archetype as usize];
             let bundle = T::get(|ty, size| source_arch.get_dynamic(ty, size, old_index))?;
@@ -472,8 +1146,10 @@ impl World {
                 target as usize,
             );
             let target_index = target_arch.allocate(entity.id);
-            loc.archetype = target;
-            loc.index = target_index;
+            self.entities.meta[entity.id as usize].location = Location {
+                archetype: target,
+                index: target_index,
+            };
             if let Some(moved) = source_arch.move_to(old_index, |src, ty, size| {
                 // Only move the components present in the target archetype, i.e. the non-removed ones.
                 if let Some(dst) = target_arch.get_dynamic(ty, size, target_index) {
@@ -493,6 +1169,37 @@ impl World {
         self.remove::<(T,)>(entity).map(|(x,)| x)
     }
 
+    /// Replace the `T` component of `entity` with `value`, returning the old value
+    ///
+    /// Unlike `insert`, this never moves the entity to a different archetype. If `entity` has no
+    /// `T`, nothing is stored and `value` is handed back alongside the error.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let e = world.spawn((123,));
+    /// assert_eq!(world.exchange(e, 456), Ok(123));
+    /// assert_eq!(*world.get::<i32>(e).unwrap(), 456);
+    /// let (rejected, _) = world.exchange(e, true).unwrap_err();
+    /// assert!(rejected);
+    /// ```
+    pub fn exchange<T: Component>(
+        &mut self,
+        entity: Entity,
+        value: T,
+    ) -> Result<T, (T, ComponentError)> {
+        let loc = match self.entities.get(entity) {
+            Ok(loc) => loc,
+            Err(e) => return Err((value, e.into())),
+        };
+        let column = match self.archetypes[loc.archetype as usize].get::<T>() {
+            Some(column) => column,
+            None => return Err((value, MissingComponent::new::<T>().into())),
+        };
+        Ok(unsafe { mem::replace(&mut *column.as_ptr().add(loc.index as usize), value) })
+    }
+
     /// Borrow the `T` component of `entity` without safety checks
     ///
     /// Should only be used as a building block for safe abstractions.
@@ -521,6 +1228,7 @@ impl World {
     ///
     /// `entity` must have been previously obtained from this `World`, and no borrow of the same
     /// component of `entity` may be live simultaneous to the returned reference.
+    #[allow(clippy::mut_from_ref)]
     pub unsafe fn get_unchecked_mut<T: Component>(
         &self,
         entity: Entity,
@@ -536,6 +1244,14 @@ impl World {
             .add(loc.index as usize))
     }
 
+    /// Apply the commands recorded in `buffer`, in the order they were recorded
+    ///
+    /// Commands targeting entities that no longer exist are skipped, dropping any components they
+    /// carried. See `CommandBuffer`.
+    pub fn apply(&mut self, mut buffer: CommandBuffer) {
+        buffer.run_on(self);
+    }
+
     /// Convert all reserved entities into empty entities that can be iterated and accessed
     ///
     /// Invoked implicitly by `spawn`, `despawn`, `insert`, and `remove`.
@@ -559,6 +1275,55 @@ impl World {
         self.archetypes.iter()
     }
 
+    /// Number of archetypes, i.e. distinct sets of components, that entities have been stored with
+    ///
+    /// Equal to `archetypes().len()`. Archetypes are never freed, so a steadily climbing count is
+    /// a sign of archetype explosion. Each one's `len` and `component_types` show where entities
+    /// are concentrated.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let initial = world.archetype_count();
+    /// world.spawn((123, true));
+    /// world.spawn((456,));
+    /// assert_eq!(world.archetype_count(), initial + 2);
+    /// ```
+    pub fn archetype_count(&self) -> u32 {
+        self.archetypes.len() as u32
+    }
+
+    /// Find the position in `archetypes` of the archetype holding exactly the components `types`
+    ///
+    /// Order and duplicates in `types` are ignored. Returns `None` if no entity has ever had that
+    /// exact set of components.
+    ///
+    /// The internal archetype index is keyed by component alignment, which can't be recovered from
+    /// bare `TypeId`s, so this scans every archetype instead, taking O(archetypes × types) time.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// # use std::any::TypeId;
+    /// let mut world = World::new();
+    /// world.spawn((123, true));
+    /// let index = world
+    ///     .archetype_by_types(&[TypeId::of::<bool>(), TypeId::of::<i32>()])
+    ///     .unwrap();
+    /// let archetype = world.archetypes().nth(index).unwrap();
+    /// assert!(archetype.access::<(&i32, &bool)>().is_some());
+    /// assert_eq!(world.archetype_by_types(&[TypeId::of::<bool>()]), None);
+    /// ```
+    pub fn archetype_by_types(&self, types: &[TypeId]) -> Option<usize> {
+        let mut types = types.to_vec();
+        types.sort_unstable();
+        types.dedup();
+        self.archetypes.iter().position(|arch| {
+            arch.types().len() == types.len() && types.iter().all(|&id| arch.has_dynamic(id))
+        })
+    }
+
     /// Returns a distinct value after `archetypes` is changed
     ///
     /// Store the current value after deriving information from `archetypes`, then check whether the
@@ -580,6 +1345,42 @@ impl World {
     pub fn archetypes_generation(&self) -> ArchetypesGeneration {
         ArchetypesGeneration(self.archetype_generation)
     }
+
+    /// Compute summary statistics describing how entities are distributed among archetypes
+    ///
+    /// Useful for diagnosing performance problems caused by fragmentation into many small
+    /// archetypes, e.g. from a proliferation of unique marker components.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// world.spawn((123, "abc"));
+    /// world.spawn((456, "def"));
+    /// world.spawn((true,));
+    /// let stats = world.stats();
+    /// assert_eq!(stats.entities, 3);
+    /// assert_eq!(stats.largest_archetype, 2);
+    /// assert_eq!(stats.single_entity_archetypes, 1);
+    /// ```
+    pub fn stats(&self) -> WorldStats {
+        let mut stats = WorldStats {
+            archetypes: self.archetypes.len() as u32,
+            entities: 0,
+            largest_archetype: 0,
+            single_entity_archetypes: 0,
+            capacity: 0,
+        };
+        for archetype in &self.archetypes {
+            stats.entities += archetype.len();
+            stats.largest_archetype = stats.largest_archetype.max(archetype.len());
+            if archetype.len() == 1 {
+                stats.single_entity_archetypes += 1;
+            }
+            stats.capacity += archetype.capacity();
+        }
+        stats
+    }
 }
 
 unsafe impl Send for World {}
@@ -670,9 +1471,9 @@ impl<'a> Iter<'a> {
 unsafe impl Send for Iter<'_> {}
 unsafe impl Sync for Iter<'_> {}
 
-impl<'a> Iterator for Iter<'a> {
-    type Item = (Entity, EntityRef<'a>);
-    fn next(&mut self) -> Option<Self::Item> {
+impl<'a> Iter<'a> {
+    /// Advance to the next entity, returning its handle and where its components are stored
+    fn next_row(&mut self) -> Option<(Entity, &'a Archetype, u32)> {
         loop {
             match self.current {
                 None => {
@@ -680,30 +1481,59 @@ impl<'a> Iterator for Iter<'a> {
                     self.index = 0;
                 }
                 Some(current) => {
-                    if self.index == current.len() as u32 {
+                    if self.index == current.len() {
                         self.current = None;
                         continue;
                     }
                     let index = self.index;
                     self.index += 1;
                     let id = current.entity_id(index);
-                    return Some((
-                        Entity {
-                            id,
-                            generation: self.entities.meta[id as usize].generation,
-                        },
-                        unsafe { EntityRef::new(current, index) },
-                    ));
+                    let entity = Entity {
+                        id,
+                        generation: self.entities.meta[id as usize].generation,
+                    };
+                    return Some((entity, current, index));
                 }
             }
         }
     }
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (Entity, EntityRef<'a>);
+    fn next(&mut self) -> Option<Self::Item> {
+        let (entity, archetype, index) = self.next_row()?;
+        Some((entity, unsafe { EntityRef::new(archetype, index) }))
+    }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
         (0, Some(self.entities.meta.len()))
     }
 }
 
+/// Iterator over type-erased, exclusive access to all of a world's entities
+///
+/// Obtained from `World::query_components_mut`.
+pub struct ComponentsMutIter<'a> {
+    inner: Iter<'a>,
+}
+
+unsafe impl Send for ComponentsMutIter<'_> {}
+unsafe impl Sync for ComponentsMutIter<'_> {}
+
+impl<'a> Iterator for ComponentsMutIter<'a> {
+    type Item = (Entity, ComponentsMut<'a>);
+    fn next(&mut self) -> Option<Self::Item> {
+        let (entity, archetype, index) = self.inner.next_row()?;
+        // Each entity is yielded once, so the resulting accesses never overlap
+        Some((entity, unsafe { ComponentsMut::new(archetype, index) }))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
 impl<A: DynamicBundle> Extend<A> for World {
     fn extend<T>(&mut self, iter: T)
     where
@@ -727,6 +1557,73 @@ impl<A: DynamicBundle> core::iter::FromIterator<A> for World {
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct ArchetypesGeneration(u64);
 
+/// Summary of how a world's entities are distributed among archetypes, from `World::stats`
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct WorldStats {
+    /// Number of archetypes, including the archetype of entities with no components
+    pub archetypes: u32,
+    /// Number of entities stored across all archetypes
+    pub entities: u32,
+    /// Number of entities in the most populous archetype
+    pub largest_archetype: u32,
+    /// Number of archetypes containing exactly one entity, a symptom of fragmentation
+    pub single_entity_archetypes: u32,
+    /// Number of entities that could be stored across all archetypes without reallocating
+    pub capacity: u32,
+}
+
+impl WorldStats {
+    /// Mean number of entities per archetype
+    pub fn average_archetype_len(&self) -> f32 {
+        self.entities as f32 / self.archetypes as f32
+    }
+}
+
+/// Storage for entities with exact components `T`, resolved ahead of time by `World::prepare_spawn`
+///
+/// Archetypes are never removed, so a writer stays valid for the lifetime of the `World` that
+/// created it.
+pub struct ArchetypeWriter<T> {
+    archetype_id: u32,
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<T: Bundle> ArchetypeWriter<T> {
+    /// Create an entity with `components` in the prepared archetype
+    ///
+    /// The writer records only the position of its archetype, so it is checked against `world`
+    /// by that alone. Panics if the archetype at that position in `world` does not hold exactly
+    /// the components `T`, as is likely when `world` isn't the world the writer was prepared for.
+    /// Another world with a matching archetype at the same position is written to as normal.
+    pub fn spawn(&self, world: &mut World, components: T) -> Entity {
+        // Ensure all entity allocations are accounted for so `world.entities` can realloc if
+        // necessary
+        world.flush();
+
+        let valid = world
+            .archetypes
+            .get(self.archetype_id as usize)
+            .is_some_and(|x| {
+                T::with_static_ids(|ids| x.types().iter().map(|ty| ty.id()).eq(ids.iter().copied()))
+            });
+        assert!(
+            valid,
+            "ArchetypeWriter used with a world lacking its archetype"
+        );
+        let entity = world.entities.alloc();
+        world.spawn_inner(self.archetype_id, entity, components);
+        entity
+    }
+}
+
+impl<T> Clone for ArchetypeWriter<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ArchetypeWriter<T> {}
+
 /// Entity IDs created by `World::spawn_batch`
 pub struct SpawnBatchIter<'a, I>
 where
@@ -306,21 +306,15 @@ fn query_batched() {
     let c = world.spawn((42,));
     assert_eq!(world.query::<()>().iter_batched(1).count(), 3);
     assert_eq!(world.query::<()>().iter_batched(2).count(), 2);
-    assert_eq!(
-        world.query::<()>().iter_batched(2).flat_map(|x| x).count(),
-        3
-    );
+    assert_eq!(world.query::<()>().iter_batched(2).flatten().count(), 3);
     // different archetypes are always in different batches
     assert_eq!(world.query::<()>().iter_batched(3).count(), 2);
-    assert_eq!(
-        world.query::<()>().iter_batched(3).flat_map(|x| x).count(),
-        3
-    );
+    assert_eq!(world.query::<()>().iter_batched(3).flatten().count(), 3);
     assert_eq!(world.query::<()>().iter_batched(4).count(), 2);
     let entities = world
         .query::<()>()
         .iter_batched(1)
-        .flat_map(|x| x)
+        .flatten()
         .map(|(e, ())| e)
         .collect::<Vec<_>>();
     dbg!(&entities);
@@ -358,3 +352,1699 @@ fn query_one() {
     world.despawn(a).unwrap();
     assert!(world.query_one::<&i32>(a).is_err());
 }
+
+#[test]
+fn spawn_cloned() {
+    #[derive(Clone, Debug, PartialEq)]
+    struct Stats {
+        hp: u32,
+        speed: f32,
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Name(String);
+
+    let stats = Stats { hp: 10, speed: 1.5 };
+    let name = Name("orc".into());
+
+    let mut world = World::new();
+    let a = world.spawn_cloned((&stats, &name));
+    let b = world.spawn_cloned((&stats, &name));
+    assert_ne!(a, b);
+    for e in [a, b].iter() {
+        assert_eq!(*world.get::<Stats>(*e).unwrap(), stats);
+        assert_eq!(*world.get::<Name>(*e).unwrap(), name);
+    }
+}
+
+#[test]
+fn weak_query_skips_despawned() {
+    let mut world = World::new();
+    let a = world.spawn(("abc", 123));
+    let b = world.spawn(("def", 456));
+    let c = world.spawn(("ghi", 789));
+    let mut weak = world.weak_query::<&i32>();
+
+    let (first, mut query) = weak.next(&world).unwrap();
+    let first_value = *query.get().unwrap();
+    drop(query);
+    let victim = [a, b, c].iter().copied().find(|&e| e != first).unwrap();
+    world.despawn(victim).unwrap();
+    world.spawn(("jkl", 1000));
+
+    let mut seen = vec![(first, first_value)];
+    while let Some((e, mut query)) = weak.next(&world) {
+        seen.push((e, *query.get().unwrap()));
+    }
+    assert_eq!(seen.len(), 2);
+    assert!(seen.iter().all(|&(e, _)| e != victim));
+    assert!(seen.iter().all(|&(_, x)| x != 1000));
+}
+
+#[test]
+fn stats_fragmentation() {
+    struct Marker<const N: usize>;
+
+    let mut world = World::new();
+    world.spawn_batch((0..10).map(|i| (i,)));
+    world.spawn((0, Marker::<0>));
+    world.spawn((0, Marker::<1>));
+    world.spawn((0, Marker::<2>));
+    let stats = world.stats();
+    // The empty archetype, `(i32,)`, and one per marker
+    assert_eq!(stats.archetypes, 5);
+    assert_eq!(stats.entities, 13);
+    assert_eq!(stats.largest_archetype, 10);
+    assert_eq!(stats.single_entity_archetypes, 3);
+    assert!(stats.capacity >= stats.entities);
+    assert_eq!(stats.average_archetype_len(), 13.0 / 5.0);
+}
+
+#[test]
+fn query_or_default() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static DEFAULTS: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Debug, PartialEq)]
+    struct Speed(f32);
+
+    impl Default for Speed {
+        fn default() -> Self {
+            DEFAULTS.fetch_add(1, Ordering::Relaxed);
+            Speed(1.0)
+        }
+    }
+
+    let mut world = World::new();
+    let a = world.spawn(("abc", Speed(3.0)));
+    let b = world.spawn(("def",));
+    let c = world.spawn(("ghi", Speed(0.5), true));
+    let d = world.spawn(("jkl", 7));
+    let e = world.spawn(("mno",));
+    let ents = world
+        .query::<(&&str, OrDefault<Speed>)>()
+        .iter()
+        .map(|(e, (_, speed))| (e, speed.0))
+        .collect::<Vec<_>>();
+    assert_eq!(ents.len(), 5);
+    assert!(ents.contains(&(a, 3.0)));
+    assert!(ents.contains(&(b, 1.0)));
+    assert!(ents.contains(&(c, 0.5)));
+    assert!(ents.contains(&(d, 1.0)));
+    assert!(ents.contains(&(e, 1.0)));
+    // One default per archetype lacking the component, however many entities it holds
+    assert_eq!(DEFAULTS.load(Ordering::Relaxed), 2);
+
+    let defaults = world
+        .query_mut::<OrDefault<Speed>>()
+        .into_iter()
+        .map(|(_, speed)| speed)
+        .collect::<Vec<_>>();
+    assert_eq!(defaults.len(), 5);
+    assert_eq!(DEFAULTS.load(Ordering::Relaxed), 4);
+    assert_eq!(world.query_one_mut::<OrDefault<Speed>>(b).unwrap().0, 1.0);
+    assert_eq!(DEFAULTS.load(Ordering::Relaxed), 5);
+}
+
+#[test]
+fn query_any_of() {
+    use std::any::TypeId;
+
+    struct Persistent;
+    struct Saved;
+
+    let mut world = World::new();
+    let a = world.spawn((Persistent, 123));
+    let b = world.spawn((Saved, "abc"));
+    let c = world.spawn((Persistent, Saved));
+    world.spawn((456, "def"));
+    world.spawn(());
+
+    let persistable = vec![TypeId::of::<Persistent>(), TypeId::of::<Saved>()];
+    let mut ents = world.query_any_of(&persistable).collect::<Vec<_>>();
+    ents.sort();
+    let mut expected = vec![a, b, c];
+    expected.sort();
+    assert_eq!(ents, expected);
+    assert_eq!(world.query_any_of(&[]).count(), 0);
+}
+
+#[test]
+fn despawn_batch_take() {
+    struct Particle(Vec<f32>);
+
+    let mut world = World::new();
+    let a = world.spawn((Particle(vec![1.0]), 1));
+    let b = world.spawn((Particle(vec![2.0, 3.0]), "abc"));
+    let c = world.spawn((4,));
+    let d = world.spawn((Particle(vec![]),));
+    world.despawn(d).unwrap();
+    let survivor = world.spawn((Particle(vec![5.0]),));
+
+    let generation = world.archetypes_generation();
+    let taken = world.despawn_batch_take::<(Particle,)>(vec![a, b, c, d]);
+    assert_eq!(world.archetypes_generation(), generation);
+    let values = taken.into_iter().map(|(p,)| p.0).collect::<Vec<_>>();
+    assert_eq!(values, vec![vec![1.0], vec![2.0, 3.0]]);
+    for &e in &[a, b, c] {
+        assert!(!world.contains(e));
+    }
+    assert_eq!(world.get::<Particle>(survivor).unwrap().0, vec![5.0]);
+    assert_eq!(world.iter().count(), 1);
+}
+
+#[test]
+fn despawn_batch_take_empty_entities() {
+    let mut world = World::new();
+    let a = world.spawn(());
+    let b = world.spawn((1,));
+    let c = world.spawn(());
+    assert_eq!(world.despawn_batch_take::<()>(vec![a, b]), vec![(), ()]);
+    assert!(!world.contains(a));
+    assert!(!world.contains(b));
+    assert!(world.contains(c));
+    assert_eq!(world.despawn_batch_take::<(i32,)>(vec![c]), vec![]);
+    assert!(!world.contains(c));
+}
+
+#[test]
+fn query_single() {
+    struct Player;
+
+    let mut world = World::new();
+    assert_eq!(
+        world.query::<&i32>().with::<Player>().single(),
+        Err(QuerySingleError::NoEntities)
+    );
+
+    world.spawn((Player, 10));
+    world.spawn((20,));
+    assert_eq!(world.query::<&i32>().with::<Player>().single(), Ok(&10));
+    *world.query::<&mut i32>().with::<Player>().single().unwrap() += 1;
+    assert_eq!(world.query::<&i32>().with::<Player>().single(), Ok(&11));
+
+    world.spawn((Player, 30, true));
+    assert_eq!(
+        world.query::<&i32>().with::<Player>().single(),
+        Err(QuerySingleError::MultipleEntities)
+    );
+}
+
+#[test]
+fn query_single_mut() {
+    struct Player;
+
+    let mut world = World::new();
+    let player = world.spawn((Player, 10, true));
+    world.spawn((20, false));
+    {
+        let mut query = world.query::<(&mut i32, &mut bool)>().with::<Player>();
+        let (health, alive) = query.single().unwrap();
+        *health -= 10;
+        *alive = false;
+    }
+    assert_eq!(*world.get::<i32>(player).unwrap(), 0);
+    assert!(!*world.get::<bool>(player).unwrap());
+}
+
+#[test]
+fn repeated_shared_borrow() {
+    let mut world = World::new();
+    world.spawn(("abc", 123));
+    world.spawn(("def", 456));
+
+    let mut ents = world
+        .query::<(&i32, &i32, Option<&i32>)>()
+        .iter()
+        .map(|(_, (&a, &b, c))| (a, b, c.copied()))
+        .collect::<Vec<_>>();
+    ents.sort();
+    assert_eq!(ents, &[(123, 123, Some(123)), (456, 456, Some(456))]);
+}
+
+#[test]
+#[should_panic(expected = "already borrowed")]
+fn illegal_shared_then_unique_borrow() {
+    let mut world = World::new();
+    world.spawn(("abc", 123));
+
+    world.query::<(&i32, &mut i32)>().iter();
+}
+
+#[test]
+fn promote() {
+    let mut world = World::new();
+    let ents = (0..4).map(|i| world.spawn((i, "abc"))).collect::<Vec<_>>();
+    let other = world.spawn((10,));
+    world.promote(ents[2]).unwrap();
+
+    let order = world
+        .query::<(&i32, &&str)>()
+        .iter()
+        .map(|(e, (&i, _))| (e, i))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        order,
+        &[(ents[2], 2), (ents[1], 1), (ents[0], 0), (ents[3], 3)]
+    );
+    for (i, &e) in ents.iter().enumerate() {
+        assert_eq!(*world.get::<i32>(e).unwrap(), i as i32);
+    }
+    assert_eq!(*world.get::<i32>(other).unwrap(), 10);
+
+    world.despawn(ents[1]).unwrap();
+    world.promote(ents[3]).unwrap();
+    assert_eq!(*world.get::<i32>(ents[2]).unwrap(), 2);
+    assert_eq!(*world.get::<i32>(ents[3]).unwrap(), 3);
+    assert_eq!(
+        world
+            .query::<&i32>()
+            .with::<&str>()
+            .iter()
+            .next()
+            .unwrap()
+            .0,
+        ents[3]
+    );
+}
+
+#[test]
+fn components_mut() {
+    use std::any::TypeId;
+
+    #[derive(Debug, PartialEq)]
+    struct Position {
+        x: f32,
+        y: f32,
+    }
+
+    let mut world = World::new();
+    let e = world.spawn((Position { x: 1.0, y: 2.0 }, 7u8));
+    let f = world.spawn(());
+    {
+        let mut components = world.components_mut(e).unwrap();
+        let mut types = components.component_types().collect::<Vec<_>>();
+        types.sort();
+        let mut expected = vec![TypeId::of::<Position>(), TypeId::of::<u8>()];
+        expected.sort();
+        assert_eq!(types, expected);
+
+        let ptr = components
+            .get_mut_by_id(TypeId::of::<Position>())
+            .unwrap()
+            .cast::<Position>();
+        unsafe {
+            (*ptr).y = 5.0;
+        }
+        assert!(components.get_mut_by_id(TypeId::of::<bool>()).is_none());
+    }
+    assert_eq!(
+        *world.get::<Position>(e).unwrap(),
+        Position { x: 1.0, y: 5.0 }
+    );
+    assert_eq!(*world.get::<u8>(e).unwrap(), 7);
+
+    let mut empty = world.components_mut(f).unwrap();
+    assert_eq!(empty.component_types().count(), 0);
+    assert!(empty.get_mut_by_id(TypeId::of::<u8>()).is_none());
+}
+
+#[test]
+fn query_components_mut() {
+    use std::any::TypeId;
+
+    let mut world = World::new();
+    let a = world.spawn((1u32, 1.5f32));
+    let b = world.spawn((2u32,));
+    let c = world.spawn(("abc",));
+    let d = world.spawn((3u32,));
+    world.despawn(d).unwrap();
+    let reserved = world.reserve_entity();
+
+    let mut all = world.query_components_mut().collect::<Vec<_>>();
+    assert_eq!(all.len(), 4);
+    for (_, components) in &mut all {
+        if let Some(ptr) = components.get_mut_by_id(TypeId::of::<u32>()) {
+            unsafe {
+                *ptr.cast::<u32>() += 10;
+            }
+        }
+    }
+    let mut ids = all.iter().map(|&(e, _)| e).collect::<Vec<_>>();
+    drop(all);
+    ids.sort();
+    let mut expected = vec![a, b, c, reserved];
+    expected.sort();
+    assert_eq!(ids, expected);
+    assert_eq!(*world.get::<u32>(a).unwrap(), 11);
+    assert_eq!(*world.get::<f32>(a).unwrap(), 1.5);
+    assert_eq!(*world.get::<u32>(b).unwrap(), 12);
+}
+
+#[test]
+fn query_fold_early_exit() {
+    use std::ops::ControlFlow;
+
+    let mut world = World::new();
+    world.spawn_batch((1..=5).map(|i| (i, "abc")));
+    world.spawn((100,));
+
+    let mut visited = 0;
+    let found = world.query_fold::<(&i32, &&str), _>(Vec::new(), |mut acc, _, (&x, _)| {
+        visited += 1;
+        acc.push(x);
+        if acc.len() == 3 {
+            ControlFlow::Break(acc)
+        } else {
+            ControlFlow::Continue(acc)
+        }
+    });
+    assert_eq!(found.len(), 3);
+    assert_eq!(visited, 3);
+
+    let sum = world.query_fold::<&i32, _>(0, |acc, _, &x| ControlFlow::Continue(acc + x));
+    assert_eq!(sum, 115);
+}
+
+#[test]
+fn prepared_spawn() {
+    let mut world = World::new();
+    world.spawn(("abc",));
+    let writer = world.prepare_spawn::<(u32, &str)>();
+    let ents = (0..1000)
+        .map(|i| writer.spawn(&mut world, (i, "def")))
+        .collect::<Vec<_>>();
+    assert_eq!(world.query::<(&u32, &&str)>().iter().count(), 1000);
+    for (i, &e) in ents.iter().enumerate() {
+        assert_eq!(*world.get::<u32>(e).unwrap(), i as u32);
+    }
+    // Prepared archetypes are shared with regular spawns
+    let e = world.spawn(("ghi", 1000u32));
+    assert_eq!(world.query::<(&u32, &&str)>().iter().count(), 1001);
+    assert_eq!(*world.get::<u32>(e).unwrap(), 1000);
+}
+
+#[test]
+#[should_panic(expected = "lacking its archetype")]
+fn prepared_spawn_wrong_world() {
+    let mut a = World::new();
+    let mut b = World::new();
+    let writer = a.prepare_spawn::<(u32,)>();
+    b.prepare_spawn::<(bool,)>();
+    writer.spawn(&mut b, (1,));
+}
+
+#[test]
+fn query_lacking_component() {
+    struct Health(#[allow(dead_code)] u32);
+
+    let mut world = World::new();
+    world.spawn((Health(10), "abc"));
+    let b = world.spawn(("def",));
+    let c = world.spawn(());
+    world.spawn((Health(5),));
+    let d = world.reserve_entity();
+    world.flush();
+
+    let mut ents = world
+        .query::<Without<Health, ()>>()
+        .iter()
+        .map(|(e, ())| e)
+        .collect::<Vec<_>>();
+    ents.sort();
+    let mut expected = vec![b, c, d];
+    expected.sort();
+    assert_eq!(ents, expected);
+}
+
+#[test]
+fn scatter_gather() {
+    // Each cell writes its next value while reading its neighbors' current values
+    struct Current(f32);
+    struct Next(f32);
+    struct Neighbors(Vec<Entity>);
+
+    let mut world = World::new();
+    let cells = (0..3)
+        .map(|i| world.spawn((Current(if i == 1 { 3.0 } else { 0.0 }), Next(0.0))))
+        .collect::<Vec<_>>();
+    for (i, &cell) in cells.iter().enumerate() {
+        let neighbors = cells
+            .iter()
+            .enumerate()
+            .filter(|&(j, _)| (i as i32 - j as i32).abs() == 1)
+            .map(|(_, &e)| e)
+            .collect::<Vec<_>>();
+        world.insert_one(cell, Neighbors(neighbors)).unwrap();
+    }
+
+    for (_, (next, current, neighbors)) in world.query::<(&mut Next, &Current, &Neighbors)>().iter()
+    {
+        let inflow = neighbors
+            .0
+            .iter()
+            .map(|&n| world.get::<Current>(n).unwrap().0)
+            .sum::<f32>();
+        next.0 = current.0 + 0.25 * (inflow - neighbors.0.len() as f32 * current.0);
+    }
+
+    let next = cells
+        .iter()
+        .map(|&e| world.get::<Next>(e).unwrap().0)
+        .collect::<Vec<_>>();
+    assert_eq!(next, &[0.75, 1.5, 0.75]);
+}
+
+#[test]
+fn disjoint_mutable_columns() {
+    #[derive(Debug, PartialEq)]
+    struct Transform(f32);
+    #[derive(Debug, PartialEq)]
+    struct GlobalTransform(f32);
+
+    let mut world = World::new();
+    let a = world.spawn((Transform(1.0), GlobalTransform(0.0)));
+    let b = world.spawn((Transform(2.0), GlobalTransform(0.0), "abc"));
+    for (_, (local, global)) in world
+        .query::<(&mut Transform, &mut GlobalTransform)>()
+        .iter()
+    {
+        local.0 += 1.0;
+        global.0 = local.0 * 10.0;
+    }
+    assert_eq!(*world.get::<Transform>(a).unwrap(), Transform(2.0));
+    assert_eq!(
+        *world.get::<GlobalTransform>(a).unwrap(),
+        GlobalTransform(20.0)
+    );
+    assert_eq!(*world.get::<Transform>(b).unwrap(), Transform(3.0));
+    assert_eq!(
+        *world.get::<GlobalTransform>(b).unwrap(),
+        GlobalTransform(30.0)
+    );
+}
+
+#[test]
+fn query_find() {
+    let mut world = World::new();
+    world.spawn(("abc", 1));
+    let b = world.spawn(("def", 2));
+    world.spawn(("ghi", 2, true));
+
+    let mut visited = 0;
+    let found = world.query::<(&&str, &i32)>().find(|_, (_, &x)| {
+        visited += 1;
+        x == 2
+    });
+    assert_eq!(found, Some(b));
+    assert_eq!(visited, 2);
+    assert_eq!(world.query::<&i32>().find(|_, &x| x > 2), None);
+}
+
+#[test]
+fn insert_batch() {
+    let mut world = World::new();
+    let a = world.spawn((1,));
+    let b = world.spawn((2, "abc"));
+    let c = world.spawn((3,));
+    let d = world.spawn((4, true));
+    world
+        .insert_batch(&[a, b, c, d], vec![1.0, 2.0, 3.0, 4.0])
+        .unwrap();
+    assert_eq!(*world.get::<f64>(a).unwrap(), 1.0);
+    assert_eq!(*world.get::<f64>(b).unwrap(), 2.0);
+    assert_eq!(*world.get::<f64>(c).unwrap(), 3.0);
+    assert_eq!(*world.get::<f64>(d).unwrap(), 4.0);
+    assert_eq!(*world.get::<&str>(b).unwrap(), "abc");
+    assert!(*world.get::<bool>(d).unwrap());
+    assert_eq!(*world.get::<i32>(c).unwrap(), 3);
+    assert_eq!(world.query::<(&i32, &f64)>().iter().count(), 4);
+
+    world.insert_batch(&[b], vec![5.0]).unwrap();
+    assert_eq!(*world.get::<f64>(b).unwrap(), 5.0);
+
+    world.despawn(c).unwrap();
+    assert!(world.insert_batch(&[a, c], vec![9.0, 9.0]).is_err());
+    assert_eq!(*world.get::<f64>(a).unwrap(), 1.0);
+}
+
+#[test]
+fn insert_batch_empty_entities() {
+    let mut world = World::new();
+    let a = world.spawn(());
+    let b = world.spawn(());
+    let c = world.spawn((1,));
+    world.insert_batch(&[a, b, c], vec![1.0, 2.0, 3.0]).unwrap();
+    assert_eq!(*world.get::<f64>(a).unwrap(), 1.0);
+    assert_eq!(*world.get::<f64>(b).unwrap(), 2.0);
+    assert_eq!(*world.get::<f64>(c).unwrap(), 3.0);
+    assert_eq!(world.query::<&f64>().iter().count(), 3);
+}
+
+#[test]
+fn static_reference_components() {
+    static BYTES: [u8; 3] = [1, 2, 3];
+    let mut world = World::new();
+    let a = world.spawn(("abc", &BYTES[..]));
+    let b = world.spawn(
+        EntityBuilder::new()
+            .add(true)
+            .add("def")
+            .add(&BYTES[1..])
+            .build(),
+    );
+    assert_eq!(*world.get::<&'static str>(a).unwrap(), "abc");
+    assert_eq!(*world.get::<&'static [u8]>(b).unwrap(), &[2, 3]);
+    let mut results = world
+        .query::<(&&'static str, &&'static [u8])>()
+        .iter()
+        .map(|(e, (s, bytes))| (e, *s, *bytes))
+        .collect::<Vec<_>>();
+    results.sort_by_key(|x| x.0.id());
+    assert_eq!(results, [(a, "abc", &BYTES[..]), (b, "def", &BYTES[1..])]);
+    assert_eq!(world.remove_one::<&'static [u8]>(a), Ok(&BYTES[..]));
+}
+
+#[test]
+fn query_len_exact() {
+    let mut world = World::new();
+    world.spawn((1, true));
+    world.spawn((2, true));
+    world.spawn((3, "abc"));
+    world.spawn(("def",));
+    let mut query = world.query::<&i32>();
+    let mut iter = query.iter();
+    assert_eq!(iter.len(), 3);
+    assert_eq!(iter.size_hint(), (3, Some(3)));
+    iter.next();
+    assert_eq!(iter.len(), 2);
+    iter.next();
+    iter.next();
+    assert_eq!(iter.len(), 0);
+    assert!(iter.next().is_none());
+    drop(query);
+    let ints = world.query::<With<bool, &i32>>().iter().len();
+    assert_eq!(ints, 2);
+}
+
+#[test]
+fn query_one_three_components() {
+    let mut world = World::new();
+    let a = world.spawn((123, true, "abc"));
+    let b = world.spawn((456, false));
+    assert_eq!(
+        world
+            .query_one::<(&i32, &bool, &&str)>(a)
+            .unwrap()
+            .get()
+            .map(|(&x, &y, &z)| (x, y, z)),
+        Some((123, true, "abc"))
+    );
+    assert!(world
+        .query_one::<(&i32, &bool, &&str)>(b)
+        .unwrap()
+        .get()
+        .is_none());
+    world.despawn(a).unwrap();
+    assert!(world.query_one::<(&i32, &bool, &&str)>(a).is_err());
+}
+
+#[test]
+fn archetype_by_types() {
+    use std::any::TypeId;
+    let mut world = World::new();
+    world.spawn((123, "abc"));
+    world.spawn((true,));
+    let index = world
+        .archetype_by_types(&[
+            TypeId::of::<&str>(),
+            TypeId::of::<i32>(),
+            TypeId::of::<i32>(),
+        ])
+        .unwrap();
+    let archetype = world.archetypes().nth(index).unwrap();
+    assert!(archetype.access::<(&i32, &&str)>().is_some());
+    assert!(archetype.access::<&bool>().is_none());
+    assert_eq!(world.archetype_by_types(&[]), Some(0));
+    assert_eq!(world.archetype_by_types(&[TypeId::of::<i32>()]), None);
+    assert_eq!(
+        world.archetype_by_types(&[TypeId::of::<bool>(), TypeId::of::<f32>()]),
+        None
+    );
+}
+
+#[test]
+fn entity_component_types() {
+    use std::any::TypeId;
+    let mut world = World::new();
+    let a = world.spawn((123, true));
+    let b = world.spawn(("abc",));
+    let c = world.spawn(());
+    let mut seen = Vec::new();
+    for (entity, components) in &world {
+        let mut types = components.component_types().collect::<Vec<_>>();
+        types.sort();
+        seen.push((entity, types));
+    }
+    seen.sort_by_key(|x| x.0.id());
+    let mut a_types = vec![TypeId::of::<i32>(), TypeId::of::<bool>()];
+    a_types.sort();
+    assert_eq!(
+        seen,
+        [(a, a_types), (b, vec![TypeId::of::<&str>()]), (c, vec![])]
+    );
+}
+
+#[test]
+fn shrink_archetype() {
+    use std::any::TypeId;
+    let mut world = World::new();
+    let spike = world
+        .spawn_batch((0..100).map(|i| (i, true)))
+        .collect::<Vec<_>>();
+    world
+        .spawn_batch((0..100).map(|i| (i as f32,)))
+        .for_each(|_| {});
+    let kept = spike[7];
+    for &e in &spike {
+        if e != kept {
+            world.despawn(e).unwrap();
+        }
+    }
+    let spiked = world
+        .archetype_by_types(&[TypeId::of::<i32>(), TypeId::of::<bool>()])
+        .unwrap();
+    let other = world.archetype_by_types(&[TypeId::of::<f32>()]).unwrap();
+    let capacity = |world: &World, i: usize| world.archetypes().nth(i).unwrap().capacity();
+    assert!(capacity(&world, spiked) >= 100);
+    let other_before = capacity(&world, other);
+
+    world.shrink_archetype_for::<(i32, bool)>();
+    assert_eq!(capacity(&world, spiked), 1);
+    assert_eq!(capacity(&world, other), other_before);
+    assert_eq!(*world.get::<i32>(kept).unwrap(), 7);
+    assert_eq!(world.query::<&f32>().iter().count(), 100);
+
+    // Unknown archetypes are ignored
+    world.shrink_archetype_for::<(&str,)>();
+}
+
+#[test]
+fn query_copied() {
+    let mut world = World::new();
+    for i in 0..10u32 {
+        world.spawn((i, i % 2 == 0));
+    }
+    world.spawn((100u32,));
+    let by_value = world
+        .query::<(Copied<u32>, &bool)>()
+        .iter()
+        .map(|(_, (x, _))| x)
+        .sum::<u32>();
+    let by_ref = world
+        .query::<(&u32, &bool)>()
+        .iter()
+        .map(|(_, (&x, _))| x)
+        .sum::<u32>();
+    assert_eq!(by_value, 45);
+    assert_eq!(by_value, by_ref);
+    assert_eq!(
+        world
+            .query::<Copied<u32>>()
+            .iter()
+            .map(|(_, x)| x)
+            .sum::<u32>(),
+        145
+    );
+}
+
+#[test]
+fn query_joins_across_archetypes() {
+    let mut world = World::new();
+    let both = world.spawn((1, 'a'));
+    world.spawn((2,));
+    world.spawn(('b',));
+    let both_extra = world.spawn((3, 'c', true));
+    let mut joined = world
+        .query::<(&i32, &char)>()
+        .iter()
+        .map(|(e, (&i, &c))| (e, i, c))
+        .collect::<Vec<_>>();
+    joined.sort_by_key(|x| x.1);
+    assert_eq!(joined, [(both, 1, 'a'), (both_extra, 3, 'c')]);
+}
+
+#[test]
+fn query_mut() {
+    let mut world = World::new();
+    let a = world.spawn((1, true));
+    let b = world.spawn((2, "abc"));
+    let c = world.spawn((3,));
+    for (_, x) in world.query_mut::<&mut i32>() {
+        *x *= 10;
+    }
+    let mut query = world.query_mut::<&mut i32>().with::<bool>();
+    for (_, x) in query.iter() {
+        *x += 1;
+    }
+    assert_eq!(query.iter().count(), 1);
+    let mut seen = world
+        .query_mut::<&i32>()
+        .without::<bool>()
+        .into_iter()
+        .map(|(e, &x)| (e, x))
+        .collect::<Vec<_>>();
+    seen.sort_by_key(|x| x.1);
+    assert_eq!(seen, [(b, 20), (c, 30)]);
+    assert_eq!(*world.get::<i32>(a).unwrap(), 11);
+}
+
+#[test]
+#[should_panic]
+fn query_mut_aliasing() {
+    let mut world = World::new();
+    world.spawn((1,));
+    world.query_mut::<(&mut i32, &i32)>();
+}
+
+#[test]
+fn collect_entities_sorted() {
+    let mut world = World::new();
+    let entities = (0..20)
+        .map(|i| {
+            if i % 3 == 0 {
+                world.spawn((i, true))
+            } else {
+                world.spawn((i,))
+            }
+        })
+        .collect::<Vec<_>>();
+    world.despawn(entities[4]).unwrap();
+    world.spawn((true,));
+    world.spawn((-1,));
+    let mut query = world.query::<&i32>();
+    let sorted = query.collect_entities_sorted();
+    assert!(sorted.windows(2).all(|x| x[0].id() < x[1].id()));
+    let mut expected = query.iter().map(|(e, _)| e).collect::<Vec<_>>();
+    expected.sort_by_key(|x| x.id());
+    assert_eq!(sorted, expected);
+    assert_eq!(sorted.len(), 20);
+}
+
+#[test]
+fn remove_relocates_neighbours() {
+    let mut world = World::new();
+    let a = world.spawn((1, "a", true));
+    let b = world.spawn((2, "b", false));
+    let c = world.spawn((3, "c", true));
+    assert_eq!(world.remove_one::<&str>(a), Ok("a"));
+    // `c` was swapped into `a`'s old slot and must still be reachable
+    assert_eq!(*world.get::<&str>(c).unwrap(), "c");
+    assert_eq!(*world.get::<&str>(b).unwrap(), "b");
+    assert_eq!(*world.get::<i32>(a).unwrap(), 1);
+    assert!(*world.get::<bool>(a).unwrap());
+    assert!(world.get::<&str>(a).is_err());
+
+    world.despawn(b).unwrap();
+    let d = world.spawn((4, "d"));
+    assert_eq!(d.id(), b.id());
+    assert_eq!(
+        world.remove_one::<i32>(b),
+        Err(ComponentError::NoSuchEntity)
+    );
+    assert_eq!(*world.get::<i32>(d).unwrap(), 4);
+}
+
+#[test]
+fn insert_overwrite_drops() {
+    use std::sync::Arc;
+    let old = Arc::new(());
+    let new = Arc::new(());
+    let mut world = World::new();
+    let e = world.spawn((Arc::clone(&old),));
+    world.insert(e, (Arc::clone(&new), 42)).unwrap();
+    assert_eq!(Arc::strong_count(&old), 1);
+    assert_eq!(Arc::strong_count(&new), 2);
+    assert!(Arc::ptr_eq(&*world.get::<Arc<()>>(e).unwrap(), &new));
+    assert_eq!(*world.get::<i32>(e).unwrap(), 42);
+
+    // Overwriting in place, without changing archetype, drops as well
+    world.insert_one(e, Arc::clone(&old)).unwrap();
+    assert_eq!(Arc::strong_count(&new), 1);
+    assert_eq!(Arc::strong_count(&old), 2);
+    world.despawn(e).unwrap();
+    assert_eq!(Arc::strong_count(&old), 1);
+}
+
+#[test]
+fn drop_world_drops_components() {
+    use std::sync::Arc;
+    let value = Arc::new(());
+    let mut world = World::new();
+    for i in 0..10 {
+        world.spawn((Arc::clone(&value), i));
+    }
+    let moved = world.spawn((Arc::clone(&value),));
+    world.insert_one(moved, true).unwrap();
+    let removed = world.spawn((Arc::clone(&value),));
+    world.despawn(removed).unwrap();
+    assert_eq!(Arc::strong_count(&value), 12);
+    drop(world);
+    assert_eq!(Arc::strong_count(&value), 1);
+}
+
+#[test]
+fn despawn_drops_components() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+    struct Counted;
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            DROPS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let mut world = World::new();
+    let survivor = world.spawn((Counted, 0));
+    let first = world.spawn((Counted, 1));
+    world.despawn(first).unwrap();
+    for i in 0..100 {
+        let e = world.spawn((Counted, i));
+        assert_eq!(e.id(), first.id());
+        world.despawn(e).unwrap();
+    }
+    assert_eq!(DROPS.load(Ordering::Relaxed), 101);
+    assert!(world.contains(survivor));
+    assert_eq!(world.stats().entities, 1);
+}
+
+#[test]
+fn despawn_recycles_id_with_new_generation() {
+    let mut world = World::new();
+    let a = world.spawn((1,));
+    world.despawn(a).unwrap();
+    let b = world.spawn((2,));
+    assert_eq!(b.id(), a.id());
+    assert_ne!(b, a);
+    assert_eq!(format!("{:?}", a), "0v1");
+    assert_eq!(format!("{:?}", b), "0v2");
+    assert!(!world.contains(a));
+    assert!(world.get::<i32>(a).is_err());
+    assert_eq!(*world.get::<i32>(b).unwrap(), 2);
+    assert_eq!(world.spawn(()).id(), 1);
+}
+
+#[test]
+fn has_component() {
+    let mut world = World::new();
+    let a = world.spawn((123, true));
+    let b = world.reserve_entity();
+    assert!(world.has::<i32>(a));
+    assert!(world.has::<bool>(a));
+    assert!(!world.has::<&str>(a));
+    assert!(world.contains(b));
+    assert!(!world.has::<i32>(b));
+    world.remove_one::<bool>(a).unwrap();
+    assert!(world.has::<i32>(a));
+    assert!(!world.has::<bool>(a));
+    world.despawn(a).unwrap();
+    assert!(!world.contains(a));
+    assert!(!world.has::<i32>(a));
+}
+
+#[test]
+fn entity_stable_across_moves() {
+    let mut world = World::new();
+    let others = (0..4).map(|i| world.spawn((i,))).collect::<Vec<_>>();
+    let e = world.spawn((42,));
+    world.insert_one(e, true).unwrap();
+    world.insert_one(e, "abc").unwrap();
+    world.remove_one::<i32>(e).unwrap();
+    world.insert_one(others[0], true).unwrap();
+    world.insert_one(e, 7u8).unwrap();
+    let entity = world.entity(e).unwrap();
+    assert!(entity.get::<i32>().is_none());
+    assert!(*entity.get::<bool>().unwrap());
+    assert_eq!(*entity.get::<&str>().unwrap(), "abc");
+    assert_eq!(*entity.get::<u8>().unwrap(), 7);
+    for (i, &other) in others.iter().enumerate() {
+        assert_eq!(*world.get::<i32>(other).unwrap(), i as i32);
+    }
+}
+
+#[test]
+fn spawn_batch_handles_in_order() {
+    let mut world = World::new();
+    let freed = world.spawn((0, "freed"));
+    world.despawn(freed).unwrap();
+    let entities = world
+        .spawn_batch((0..1000).map(|i| (i, "abc")))
+        .collect::<Vec<_>>();
+    assert_eq!(entities.len(), 1000);
+    for (i, &e) in entities.iter().enumerate() {
+        assert_eq!(*world.get::<i32>(e).unwrap(), i as i32);
+    }
+    assert!(entities.iter().any(|e| e.id() == freed.id()));
+    assert!(!entities.contains(&freed));
+}
+
+#[test]
+fn world_len() {
+    let mut world = World::new();
+    assert_eq!(world.len(), 0);
+    let entities = (0..10).map(|i| world.spawn((i,))).collect::<Vec<_>>();
+    assert_eq!(world.len(), 10);
+    for &e in &entities[..5] {
+        world.despawn(e).unwrap();
+    }
+    assert_eq!(world.len(), 5);
+    world.spawn(());
+    world.spawn_batch((0..3).map(|i| (i,))).for_each(|_| {});
+    assert_eq!(world.len(), 9);
+    world.reserve_entity();
+    world.reserve_entity();
+    world.reserve_entity();
+    world.reserve_entity();
+    assert_eq!(world.len(), 13);
+    world.flush();
+    assert_eq!(world.len(), 13);
+    assert_eq!(world.iter().count(), 13);
+    world.clear();
+    assert!(world.is_empty());
+}
+
+#[test]
+fn clear_invalidates_handles() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+    struct Counted;
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            DROPS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let mut world = World::new();
+    let a = world.spawn((Counted, 1));
+    let b = world.spawn((Counted,));
+    let reserved = world.reserve_entity();
+    world.clear();
+    assert_eq!(DROPS.load(Ordering::Relaxed), 2);
+    assert!(world.is_empty());
+    assert!(!world.contains(a));
+    assert!(!world.contains(b));
+    assert!(!world.contains(reserved));
+
+    let c = world.spawn((2,));
+    assert_eq!(c.id(), a.id());
+    assert_ne!(c, a);
+    assert!(!world.contains(a));
+    assert!(world.get::<i32>(a).is_err());
+    assert_eq!(*world.get::<i32>(c).unwrap(), 2);
+}
+
+#[test]
+fn archetype_column_order() {
+    fn columns(world: &World) -> Vec<Vec<std::any::TypeId>> {
+        world
+            .archetypes()
+            .filter(|x| x.access::<(&u8, &u64, &&str)>().is_some())
+            .map(|x| x.component_types().collect())
+            .collect()
+    }
+
+    let mut a = World::new();
+    a.spawn((1u8, 2u64, "abc"));
+    let mut b = World::new();
+    let e = b.spawn(("abc",));
+    b.insert(e, (2u64, 1u8)).unwrap();
+    let mut builder = EntityBuilder::new();
+    builder.add(2u64).add("abc").add(1u8);
+    let mut c = World::new();
+    c.spawn(builder.build());
+
+    assert_eq!(columns(&a).len(), 1);
+    assert_eq!(columns(&a), columns(&b));
+    assert_eq!(columns(&a), columns(&c));
+}
+
+#[test]
+fn combined_filters() {
+    struct Position(i32);
+    struct Frozen;
+    struct Visible;
+    let mut world = World::new();
+    let a = world.spawn((Position(1), Visible));
+    world.spawn((Position(2), Visible, Frozen));
+    world.spawn((Position(3),));
+    world.spawn((Visible, 4u8));
+    let matched = world
+        .query::<With<Visible, Without<Frozen, &Position>>>()
+        .iter()
+        .map(|(e, p)| (e, p.0))
+        .collect::<Vec<_>>();
+    assert_eq!(matched, [(a, 1)]);
+    let matched = world
+        .query::<(&Position, Option<&u8>)>()
+        .with::<Frozen>()
+        .without::<Visible>()
+        .iter()
+        .count();
+    assert_eq!(matched, 0);
+}
+
+#[test]
+fn optional_mut_component() {
+    let mut world = World::new();
+    let a = world.spawn((1, 10u8));
+    let b = world.spawn((2,));
+    for (_, (x, y)) in &mut world.query::<(&i32, Option<&mut u8>)>() {
+        if let Some(y) = y {
+            *y += *x as u8;
+        }
+    }
+    assert_eq!(*world.get::<u8>(a).unwrap(), 11);
+    let mut rows = world
+        .query::<(&i32, Option<&u8>)>()
+        .iter()
+        .map(|(e, (&x, y))| (e, x, y.copied()))
+        .collect::<Vec<_>>();
+    rows.sort_by_key(|x| x.1);
+    assert_eq!(rows, [(a, 1, Some(11)), (b, 2, None)]);
+}
+
+#[test]
+#[should_panic(expected = "multiple u32 fields")]
+fn spawn_duplicate_types() {
+    let mut world = World::new();
+    world.spawn((1u32, true, 2u32));
+}
+
+#[test]
+#[should_panic(expected = "multiple u32 fields")]
+fn insert_duplicate_types() {
+    let mut world = World::new();
+    let e = world.spawn(());
+    world.insert(e, (1u32, 2u32)).unwrap();
+}
+
+#[test]
+#[should_panic(expected = "already has a u32 component")]
+fn build_duplicate_types() {
+    let mut builder = EntityBuilder::new();
+    builder.add(1u32).add(true).add(2u32);
+}
+
+#[test]
+fn reserve_archetype_capacity() {
+    let mut world = World::new();
+    world.spawn((0.5f32,));
+    world.reserve::<(i32, bool)>(500);
+    let capacity = |world: &World| {
+        world
+            .archetypes()
+            .find(|x| x.access::<(&i32, &bool)>().is_some())
+            .unwrap()
+            .capacity()
+    };
+    let reserved = capacity(&world);
+    assert!(reserved >= 500);
+    world
+        .spawn_batch((0..500).map(|i| (i, true)))
+        .for_each(|_| {});
+    assert_eq!(capacity(&world), reserved);
+    assert_eq!(world.len(), 501);
+}
+
+#[test]
+fn builder_clear_drops_staged() {
+    use std::sync::Arc;
+    let value = Arc::new(());
+    let mut world = World::new();
+    let mut builder = EntityBuilder::new();
+    builder.add(Arc::clone(&value)).add(123);
+    builder.clear();
+    assert_eq!(Arc::strong_count(&value), 1);
+
+    builder.add(Arc::clone(&value));
+    drop(builder.build());
+    assert_eq!(Arc::strong_count(&value), 1);
+
+    builder.add(456).add(Arc::clone(&value));
+    let e = world.spawn(builder.build());
+    assert_eq!(Arc::strong_count(&value), 2);
+    assert_eq!(*world.get::<i32>(e).unwrap(), 456);
+    drop(builder);
+    world.despawn(e).unwrap();
+    assert_eq!(Arc::strong_count(&value), 1);
+}
+
+#[test]
+fn builder_get() {
+    #[repr(align(64))]
+    #[derive(Debug, PartialEq)]
+    struct Aligned(u8);
+    #[repr(align(16))]
+    struct AlignedZst;
+
+    let mut builder = EntityBuilder::new();
+    builder.add(1u8).add(Aligned(2)).add(AlignedZst).add(3u64);
+    assert!(builder.has::<Aligned>());
+    assert!(!builder.has::<&str>());
+    assert_eq!(builder.get::<Aligned>(), Some(&Aligned(2)));
+    assert_eq!(builder.get::<u64>(), Some(&3));
+    assert_eq!(
+        builder.get::<AlignedZst>().unwrap() as *const _ as usize % 16,
+        0
+    );
+    builder.get_mut::<Aligned>().unwrap().0 = 4;
+    if !builder.has::<i32>() {
+        builder.add(5);
+    }
+    let mut world = World::new();
+    let e = world.spawn(builder.build());
+    assert_eq!(*world.get::<Aligned>(e).unwrap(), Aligned(4));
+    assert_eq!(*world.get::<u8>(e).unwrap(), 1);
+    assert_eq!(*world.get::<i32>(e).unwrap(), 5);
+    assert!(!builder.has::<Aligned>());
+}
+
+#[test]
+fn get_many_mut() {
+    let mut world = World::new();
+    let a = world.spawn((1, true));
+    let b = world.spawn((2, true));
+    let c = world.spawn((3,));
+    let d = world.spawn(("abc",));
+    let dead = world.spawn((4,));
+    world.despawn(dead).unwrap();
+    let [x, y, z, w, v] = world.get_many_mut::<i32, 5>([a, b, c, d, dead]);
+    std::mem::swap(x.unwrap(), y.unwrap());
+    *z.unwrap() += 10;
+    assert!(w.is_none());
+    assert!(v.is_none());
+    assert_eq!(*world.get::<i32>(a).unwrap(), 2);
+    assert_eq!(*world.get::<i32>(b).unwrap(), 1);
+    assert_eq!(*world.get::<i32>(c).unwrap(), 13);
+}
+
+#[test]
+#[should_panic(expected = "duplicate entity")]
+fn get_many_mut_duplicate() {
+    let mut world = World::new();
+    let a = world.spawn((1,));
+    let b = world.spawn((2,));
+    world.get_many_mut::<i32, 3>([a, b, a]);
+}
+
+#[test]
+fn take_between_worlds() {
+    use std::sync::Arc;
+    let value = Arc::new(());
+    let mut a = World::new();
+    let mut b = World::new();
+    let neighbour = a.spawn((1, Arc::clone(&value)));
+    let e = a.spawn((2, Arc::clone(&value), "abc"));
+    let last = a.spawn((3, Arc::clone(&value)));
+    let taken = a.take(e).unwrap();
+    assert_eq!(Arc::strong_count(&value), 4);
+    assert_eq!(taken.get::<i32>(), Some(&2));
+    assert!(a.take(e).is_err());
+    assert_eq!(*a.get::<i32>(neighbour).unwrap(), 1);
+    assert_eq!(*a.get::<i32>(last).unwrap(), 3);
+
+    let moved = b.spawn(taken);
+    assert_eq!(Arc::strong_count(&value), 4);
+    assert_eq!(*b.get::<i32>(moved).unwrap(), 2);
+    assert_eq!(*b.get::<&str>(moved).unwrap(), "abc");
+
+    let dropped = a.take(last).unwrap();
+    drop(dropped);
+    assert_eq!(Arc::strong_count(&value), 3);
+
+    let empty = a.spawn(());
+    let id = b.spawn((true,));
+    b.insert(id, a.take(empty).unwrap()).unwrap();
+    assert!(*b.get::<bool>(id).unwrap());
+}
+
+#[test]
+#[should_panic(expected = "already borrowed")]
+fn overlapping_unique_queries() {
+    let mut world = World::new();
+    world.spawn((123,));
+    let mut a = world.query::<&mut i32>();
+    let _a = a.iter();
+    let mut b = world.query::<&mut i32>();
+    b.iter();
+}
+
+#[test]
+fn query_borrows_released_on_drop() {
+    let mut world = World::new();
+    world.spawn((123,));
+    {
+        let mut a = world.query::<&i32>();
+        let mut b = world.query::<&i32>();
+        assert_eq!(a.iter().count(), 1);
+        assert_eq!(b.iter().count(), 1);
+    }
+    let mut c = world.query::<&mut i32>();
+    for (_, x) in c.iter() {
+        *x += 1;
+    }
+    drop(c);
+    assert_eq!(
+        world.query::<&mut i32>().iter().map(|(_, x)| *x).next(),
+        Some(124)
+    );
+}
+
+#[test]
+fn exchange() {
+    let mut world = World::new();
+    let a = world.spawn((1, "abc"));
+    let b = world.spawn((2, "def"));
+    let generation = world.archetypes_generation();
+    assert_eq!(world.exchange(a, 10), Ok(1));
+    assert_eq!(world.exchange(a, "ghi"), Ok("abc"));
+    assert_eq!(
+        world.exchange(a, 1.0f32),
+        Err((
+            1.0,
+            ComponentError::MissingComponent(MissingComponent::new::<f32>())
+        ))
+    );
+    assert!(world.get::<f32>(a).is_err());
+    assert_eq!(*world.get::<i32>(a).unwrap(), 10);
+    assert_eq!(*world.get::<i32>(b).unwrap(), 2);
+    assert_eq!(world.archetypes_generation(), generation);
+    world.despawn(b).unwrap();
+    assert_eq!(world.exchange(b, 3), Err((3, ComponentError::NoSuchEntity)));
+}
+
+#[test]
+fn entity_bits_layout() {
+    let mut world = World::new();
+    let a = world.spawn(());
+    world.despawn(a).unwrap();
+    let b = world.spawn(());
+    for e in [a, b] {
+        assert_eq!(Entity::from_bits(e.to_bits()), Some(e));
+    }
+    assert_eq!(a.to_bits(), 1 << 32 | u64::from(a.id()));
+    assert_eq!(b.to_bits(), 2 << 32 | u64::from(a.id()));
+    assert_eq!(Entity::from_bits(0x0000_0002_0000_0007).unwrap().id(), 7);
+    assert_eq!(Entity::from_bits(7), None);
+}
+
+#[test]
+fn spawn_at() {
+    let mut world = World::new();
+    let far = Entity::from_bits(3 << 32 | 5000).unwrap();
+    world.spawn_at(far, (1, "far"));
+    assert_eq!(*world.get::<i32>(far).unwrap(), 1);
+    assert!(!world.contains(Entity::from_bits(1 << 32 | 5000).unwrap()));
+
+    // Fresh spawns never collide with the claimed id
+    let spawned = (0..6000).map(|_| world.spawn(())).collect::<Vec<_>>();
+    assert!(spawned.iter().all(|e| e.id() != far.id()));
+    assert_eq!(*world.get::<&str>(far).unwrap(), "far");
+
+    // Replacing a live entity despawns it
+    let neighbour = world.spawn((7,));
+    let target = world.spawn((8,));
+    let replacement = Entity::from_bits(9 << 32 | u64::from(target.id())).unwrap();
+    world.spawn_at(replacement, (true,));
+    assert!(!world.contains(target));
+    assert!(*world.get::<bool>(replacement).unwrap());
+    assert!(world.get::<i32>(replacement).is_err());
+    assert_eq!(*world.get::<i32>(neighbour).unwrap(), 7);
+
+    world.despawn(far).unwrap();
+    assert!(!world.contains(far));
+    assert_eq!(world.len(), 6000 + 2);
+}
+
+#[test]
+fn archetype_diagnostics() {
+    use std::any::TypeId;
+    let mut world = World::new();
+    world
+        .spawn_batch((0..3).map(|i| (i, true)))
+        .for_each(|_| {});
+    let e = world.spawn((1.0f32,));
+    world.despawn(e).unwrap();
+    assert_eq!(world.archetype_count() as usize, world.archetypes().len());
+    let mut info = world
+        .archetypes()
+        .filter(|x| x.component_types().len() > 0)
+        .map(|x| (x.component_types().len(), x.len(), x.is_empty()))
+        .collect::<Vec<_>>();
+    info.sort();
+    assert_eq!(info, [(1, 0, true), (2, 3, false)]);
+    let bools = world
+        .archetypes()
+        .find(|x| x.component_types().any(|t| t == TypeId::of::<bool>()))
+        .unwrap();
+    assert_eq!(bools.len(), 3);
+}
+
+#[test]
+fn query_one_mut() {
+    let mut world = World::new();
+    let a = world.spawn((1, 2.0f32));
+    let b = world.spawn((3, 4.0f32));
+    {
+        let (x, y) = world.query_one_mut::<(&mut i32, &f32)>(a).unwrap();
+        *x += *y as i32;
+    }
+    assert_eq!(*world.get::<i32>(a).unwrap(), 3);
+    assert_eq!(*world.get::<i32>(b).unwrap(), 3);
+    assert_eq!(
+        world.query_one_mut::<(&i32, &bool)>(a).unwrap_err(),
+        QueryOneError::Unsatisfied
+    );
+    assert_eq!(world.query_one_mut::<Option<&bool>>(a).unwrap(), None);
+    world.despawn(b).unwrap();
+    assert_eq!(
+        world.query_one_mut::<&i32>(b).unwrap_err(),
+        QueryOneError::NoSuchEntity
+    );
+}
+
+#[test]
+#[should_panic(expected = "already borrowed")]
+fn query_one_mut_aliasing() {
+    let mut world = World::new();
+    let a = world.spawn((1,));
+    let _ = world.query_one_mut::<(&mut i32, &mut i32)>(a);
+}
+
+#[test]
+fn option_entity_niche() {
+    assert_eq!(
+        core::mem::size_of::<Option<Entity>>(),
+        core::mem::size_of::<Entity>()
+    );
+}
+
+#[test]
+fn retain() {
+    let mut world = World::new();
+    let entities = (0..10)
+        .map(|i| world.spawn((i, i.to_string())))
+        .collect::<Vec<_>>();
+    let other = world.spawn((true,));
+    world.retain::<(&i32, &String)>(|_, (&i, s)| {
+        assert_eq!(i.to_string(), *s);
+        i % 2 == 0
+    });
+    assert_eq!(world.len(), 6);
+    assert!(world.contains(other));
+    for (i, &e) in entities.iter().enumerate() {
+        assert_eq!(world.contains(e), i % 2 == 0);
+        if i % 2 == 0 {
+            assert_eq!(*world.get::<i32>(e).unwrap(), i as i32);
+            assert_eq!(*world.get::<String>(e).unwrap(), i.to_string());
+        }
+    }
+    let mut survivors = world
+        .query::<&i32>()
+        .iter()
+        .map(|(_, &i)| i)
+        .collect::<Vec<_>>();
+    survivors.sort_unstable();
+    assert_eq!(survivors, [0, 2, 4, 6, 8]);
+
+    // Freed IDs are recycled
+    let e = world.spawn((42,));
+    assert!(entities.iter().any(|x| x.id() == e.id()));
+}
+
+#[test]
+fn command_buffer() {
+    let mut world = World::new();
+    let a = world.spawn((1, "a"));
+    let b = world.spawn((2, "b"));
+    let mut cmd = CommandBuffer::new();
+    let mut spawned = Vec::new();
+    for (id, (&x, _)) in world.query::<(&i32, &&str)>().iter() {
+        if x == 1 {
+            cmd.despawn(id);
+        } else {
+            spawned.push(cmd.spawn(&world, (x * 10, true)));
+            cmd.insert(id, (false,));
+            cmd.remove::<(&str,)>(id);
+        }
+    }
+    assert_eq!(spawned.len(), 1);
+    world.apply(cmd);
+    assert!(!world.contains(a));
+    assert!(!*world.get::<bool>(b).unwrap());
+    assert!(world.get::<&str>(b).is_err());
+    assert_eq!(*world.get::<i32>(spawned[0]).unwrap(), 20);
+    assert!(*world.get::<bool>(spawned[0]).unwrap());
+}
+
+#[test]
+fn command_buffer_ordering() {
+    let mut world = World::new();
+    let e = world.spawn(());
+    let mut cmd = CommandBuffer::new();
+    cmd.insert(e, (1,));
+    cmd.remove::<(i32,)>(e);
+    cmd.insert(e, ("abc",));
+    world.apply(cmd);
+    assert!(world.get::<i32>(e).is_err());
+    assert_eq!(*world.get::<&str>(e).unwrap(), "abc");
+
+    let mut cmd = CommandBuffer::new();
+    cmd.remove::<(&str,)>(e);
+    cmd.insert(e, ("def",));
+    cmd.insert(e, ("ghi", 2));
+    world.apply(cmd);
+    assert_eq!(*world.get::<&str>(e).unwrap(), "ghi");
+    assert_eq!(*world.get::<i32>(e).unwrap(), 2);
+}
+
+#[test]
+fn command_buffer_drops() {
+    use std::sync::Arc;
+
+    let value = Arc::new(());
+    let mut world = World::new();
+    let e = world.spawn(());
+
+    // Unapplied commands drop their components
+    let mut cmd = CommandBuffer::new();
+    cmd.insert(e, (value.clone(), 1u8));
+    cmd.insert(e, (value.clone(),));
+    assert_eq!(Arc::strong_count(&value), 3);
+    drop(cmd);
+    assert_eq!(Arc::strong_count(&value), 1);
+
+    // Components destined for dead entities are dropped
+    let mut cmd = CommandBuffer::new();
+    cmd.despawn(e);
+    cmd.insert(e, (value.clone(), 1u64));
+    world.apply(cmd);
+    assert_eq!(Arc::strong_count(&value), 1);
+
+    // Overwritten components are dropped, others moved into the world
+    let e = world.spawn(());
+    let mut cmd = CommandBuffer::new();
+    cmd.insert(e, (value.clone(),));
+    cmd.insert(e, (value.clone(), 7u16));
+    world.apply(cmd);
+    assert_eq!(Arc::strong_count(&value), 2);
+    assert_eq!(*world.get::<u16>(e).unwrap(), 7);
+    world.despawn(e).unwrap();
+    assert_eq!(Arc::strong_count(&value), 1);
+}
+
+#[test]
+fn get_absent_same_layout() {
+    #[derive(Debug, PartialEq)]
+    struct Foo(u32);
+    struct Bar(u32);
+
+    let mut world = World::new();
+    let e = world.spawn((Bar(7),));
+    assert_eq!(
+        world.get::<Foo>(e).err(),
+        Some(ComponentError::MissingComponent(
+            MissingComponent::new::<Foo>()
+        ))
+    );
+    assert!(world.get_mut::<Foo>(e).is_err());
+    assert!(world.get::<u32>(e).is_err());
+    assert!(world.entity(e).unwrap().get::<Foo>().is_none());
+    assert_eq!(world.get::<Bar>(e).unwrap().0, 7);
+}
+
+#[test]
+fn zero_sized_components() {
+    #[derive(Debug, PartialEq)]
+    struct Player;
+    #[repr(align(64))]
+    struct Aligned([u8; 0]);
+
+    let mut world = World::new();
+    let a = world.spawn((Player, 42));
+    let b = world.spawn((Player,));
+    let c = world.spawn((Aligned([]),));
+    let mut builder = EntityBuilder::new();
+    builder.add(Aligned([])).add(Player);
+    let d = world.spawn(builder.build());
+    let e = world.spawn((7,));
+
+    assert!(world.has::<Player>(a));
+    assert!(world.has::<Player>(b));
+    assert!(!world.has::<Player>(c));
+    assert!(!world.has::<Player>(e));
+    assert_eq!(*world.get::<Player>(b).unwrap(), Player);
+
+    let mut both = world
+        .query::<(&Player, &i32)>()
+        .iter()
+        .map(|(id, (_, &x))| (id, x))
+        .collect::<Vec<_>>();
+    both.sort();
+    assert_eq!(both, [(a, 42)]);
+    assert_eq!(world.query::<&Player>().iter().count(), 3);
+
+    for (_, x) in world.query::<&Aligned>().iter() {
+        assert_eq!(x as *const Aligned as usize % 64, 0);
+    }
+    assert_eq!(world.query::<&Aligned>().iter().count(), 2);
+
+    for _ in 0..100 {
+        world.spawn((Player,));
+    }
+    world.despawn(b).unwrap();
+    world.despawn(d).unwrap();
+    assert_eq!(world.query::<&Player>().iter().count(), 101);
+    assert_eq!(world.archetypes().map(|x| x.len()).sum::<u32>(), 103);
+}
+
+#[test]
+fn spawn_panic_leaks_no_id() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let mut world = World::new();
+    let a = world.spawn((1,));
+    world.despawn(a).unwrap();
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        world.spawn((1, 2));
+    }));
+    assert!(result.is_err());
+    assert_eq!(world.len(), 0);
+    assert_eq!(world.iter().count(), 0);
+
+    // The freed ID is still available, and nothing else was claimed
+    let b = world.spawn((3,));
+    assert_eq!(b.id(), a.id());
+    let c = world.spawn(());
+    assert_eq!(c.id(), a.id() + 1);
+    assert_eq!(world.len(), 2);
+}
+
+#[test]
+fn iterate_world_skips_freed() {
+    let mut world = World::new();
+    let entities = (0..5).map(|i| world.spawn((i,))).collect::<Vec<_>>();
+    world.despawn(entities[1]).unwrap();
+    world.despawn(entities[3]).unwrap();
+    let respawned = world.spawn(());
+    let _reserved = world.reserve_entity();
+
+    let mut seen = Vec::new();
+    for (entity, _) in &world {
+        assert!(world.contains(entity));
+        seen.push(entity);
+    }
+    seen.sort();
+    let mut expected = vec![entities[0], entities[2], entities[4], respawned];
+    expected.sort();
+    assert_eq!(seen, expected);
+
+    let mut seen = world.iter_entities().collect::<Vec<_>>();
+    seen.sort();
+    assert_eq!(seen, expected);
+}
+
+#[test]
+fn command_buffer_panic_drops_pending() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use std::sync::Arc;
+
+    struct Bomb;
+    impl Drop for Bomb {
+        fn drop(&mut self) {
+            panic!("boom");
+        }
+    }
+
+    let value = Arc::new(());
+    let mut world = World::new();
+    let a = world.spawn((Bomb,));
+    let b = world.spawn(());
+    let mut cmd = CommandBuffer::new();
+    cmd.insert(b, (1,));
+    cmd.remove::<(Bomb,)>(a);
+    cmd.insert(b, (value.clone(), 2u8));
+    cmd.insert(a, (value.clone(),));
+    assert_eq!(Arc::strong_count(&value), 3);
+    let result = catch_unwind(AssertUnwindSafe(|| world.apply(cmd)));
+    assert!(result.is_err());
+    assert_eq!(Arc::strong_count(&value), 1);
+    assert_eq!(*world.get::<i32>(b).unwrap(), 1);
+    assert!(world.get::<u8>(b).is_err());
+    assert!(world.get::<Bomb>(a).is_err());
+}
+
+#[test]
+fn system_context() {
+    struct Parent(Entity);
+
+    fn spawn_children(ctx: &mut SystemContext<'_>) {
+        for (id, &x) in ctx.query::<&i32>().without::<Parent>().iter() {
+            let reserved = ctx.reserve_entity();
+            ctx.insert(reserved, (x * 10, Parent(id)));
+            let child = ctx.spawn((x * 100, Parent(id)));
+            assert!(ctx.world().contains(child));
+            if x == 2 {
+                ctx.despawn(id);
+            }
+        }
+    }
+
+    let mut world = World::new();
+    let a = world.spawn((1,));
+    let b = world.spawn((2,));
+    let mut ctx = SystemContext::new(&world);
+    spawn_children(&mut ctx);
+    let cmd = ctx.into_commands();
+    world.apply(cmd);
+
+    assert!(world.contains(a));
+    assert!(!world.contains(b));
+    let mut children = world
+        .query::<(&i32, &Parent)>()
+        .iter()
+        .map(|(_, (&x, p))| (x, p.0))
+        .collect::<Vec<_>>();
+    children.sort_by_key(|x| x.0);
+    assert_eq!(children, [(10, a), (20, b), (100, a), (200, b)]);
+}
+
+#[test]
+#[should_panic(expected = "is reserved")]
+fn spawn_at_max_id() {
+    let mut world = World::new();
+    world.spawn_at(
+        Entity::from_bits(1 << 32 | u64::from(u32::MAX)).unwrap(),
+        (),
+    );
+}